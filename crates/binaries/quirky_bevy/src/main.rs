@@ -75,13 +75,8 @@ fn quirky_startup(
         quirky_device,
         queue.0.clone(),
         TextureFormat::Bgra8UnormSrgb,
-        |resources, context, surface_format| {
-            quirky_widgets::init(
-                resources,
-                context,
-                surface_format,
-                Some(QuirkyTheme::dark_default()),
-            );
+        |resources, _context, _surface_format| {
+            quirky_widgets::init(resources, Some(QuirkyTheme::dark_default()));
         },
         |r| {
             println!("wut");
@@ -89,7 +84,7 @@ fn quirky_startup(
                 .anchor_point(AnchorPoint::Center)
                 .child(
                     ButtonBuilder::new()
-                        .size_constraint(SizeConstraint::MaxSize(UVec2::new(500, 40)))
+                        .size_constraint(SizeConstraint::MaxSize(Vec2::new(500.0, 40.0)))
                         .content(
                             LabelBuilder::new()
                                 .text_align(Align::Center)
@@ -1,4 +1,4 @@
-use glam::UVec2;
+use glam::Vec2;
 use glyphon::cosmic_text::Align;
 use glyphon::{FamilyOwned, Metrics};
 use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
@@ -31,14 +31,9 @@ async fn main() {
 
     let (quirky_winit_app, quirky_app) = QuirkyWinitApp::new(
         |resources: &mut QuirkyResources,
-         context: &QuirkyAppContext,
-         surface_format: TextureFormat| {
-            quirky_widgets::init(
-                resources,
-                context,
-                surface_format,
-                Some(QuirkyTheme::dark_default()),
-            )
+         _context: &QuirkyAppContext,
+         _surface_format: TextureFormat| {
+            quirky_widgets::init(resources, Some(QuirkyTheme::dark_default()))
         },
         |_| layout,
     )
@@ -53,7 +48,7 @@ async fn main() {
 
 fn button(text: impl ToString) -> Arc<dyn Widget> {
     ButtonBuilder::new()
-        .size_constraint(SizeConstraint::MaxSize(UVec2::new(200, 100)))
+        .size_constraint(SizeConstraint::MaxSize(Vec2::new(200.0, 100.0)))
         .on_click(|_| {})
         .content(
             LabelBuilder::new()
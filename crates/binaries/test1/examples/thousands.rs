@@ -1,3 +1,5 @@
+use futures_signals::signal::Mutable;
+use quirky::clone;
 use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
 use quirky::widget::Widget;
 use quirky_widgets::layouts::box_layout::{BoxLayoutBuilder, ChildDirection};
@@ -9,112 +11,93 @@ use wgpu::TextureFormat;
 
 #[tokio::main]
 async fn main() {
+    let even_color = Mutable::new([0.0, 0.1, 0.0, 1.0]);
+
     let (quirky_winit_app, quirky_app) = QuirkyWinitApp::new(
         |resources: &mut QuirkyResources,
-         context: &QuirkyAppContext,
-         surface_format: TextureFormat| {
-            quirky_widgets::init(
-                resources,
-                context,
-                surface_format,
-                Some(QuirkyTheme::dark_default()),
-            )
+         _context: &QuirkyAppContext,
+         _surface_format: TextureFormat| {
+            quirky_widgets::init(resources, Some(QuirkyTheme::dark_default()))
         },
-        |_| thousands_layout(),
+        clone!(even_color, move |_| thousands_layout(&even_color)),
     )
     .await
     .unwrap();
 
     let draw_notifier = quirky_winit_app.get_trigger_draw_callback();
 
+    #[cfg(feature = "devtools")]
+    spawn_devtools(quirky_app.clone(), even_color);
+
     tokio::spawn(quirky_app.run(draw_notifier));
     quirky_winit_app.run_event_loop();
 }
 
-fn thousands_layout() -> Arc<dyn Widget> {
+/// Serves the devtools on the address in `QUIRKY_DEVTOOLS_ADDR`, if it is set, with the color of
+/// the even slabs exposed as the `even_color` prop
+#[cfg(feature = "devtools")]
+fn spawn_devtools(quirky_app: Arc<quirky::QuirkyApp>, even_color: Mutable<[f32; 4]>) {
+    let Ok(addr) = std::env::var("QUIRKY_DEVTOOLS_ADDR") else {
+        return;
+    };
+
+    let devtools = quirky::devtools::DevtoolsServer::new(quirky_app);
+    devtools.register_prop("even_color", even_color);
+
+    tokio::spawn(async move {
+        if let Err(e) = devtools.listen(addr.as_str()).await {
+            eprintln!("devtools server on {addr} stopped: {e:#}");
+        }
+    });
+}
+
+fn thousands_layout(even_color: &Mutable<[f32; 4]>) -> Arc<dyn Widget> {
     BoxLayoutBuilder::new()
         .child_direction(ChildDirection::Vertical)
         .children(vec![
             BoxLayoutBuilder::new()
                 .child_direction(ChildDirection::Vertical)
-                .children((0..100).map(hundreds_row).collect())
+                .children((0..100).map(|y| hundreds_row(y, even_color)).collect())
                 .build(),
             BoxLayoutBuilder::new()
                 .child_direction(ChildDirection::Vertical)
-                .children((0..100).map(hundreds_row).collect())
+                .children((0..100).map(|y| hundreds_row(y, even_color)).collect())
                 .build(),
         ])
         .build()
 }
 
-fn hundreds_row(y: i32) -> Arc<dyn Widget> {
+fn hundreds_row(y: i32, even_color: &Mutable<[f32; 4]>) -> Arc<dyn Widget> {
     BoxLayoutBuilder::new()
         .child_direction(ChildDirection::Horizontal)
         .children(vec![
             BoxLayoutBuilder::new()
                 .child_direction(ChildDirection::Horizontal)
-                .children(
-                    (0..50)
-                        .map(|x| {
-                            SlabBuilder::new()
-                                .color(if (x + y) % 2 == 0 {
-                                    [0.0, 0.1, 0.0, 1.0]
-                                } else {
-                                    [0.1, 0.0, 0.0, 1.0]
-                                })
-                                .build()
-                        })
-                        .collect(),
-                )
+                .children((0..50).map(|x| checker_slab(x + y, even_color)).collect())
                 .build(),
             BoxLayoutBuilder::new()
                 .child_direction(ChildDirection::Horizontal)
-                .children(
-                    (0..50)
-                        .map(|x| {
-                            SlabBuilder::new()
-                                .color(if (x + y) % 2 == 0 {
-                                    [0.0, 0.1, 0.0, 1.0]
-                                } else {
-                                    [0.1, 0.0, 0.0, 1.0]
-                                })
-                                .build()
-                        })
-                        .collect(),
-                )
+                .children((0..50).map(|x| checker_slab(x + y, even_color)).collect())
                 .build(),
             BoxLayoutBuilder::new()
                 .child_direction(ChildDirection::Horizontal)
-                .children(
-                    (0..50)
-                        .map(|x| {
-                            SlabBuilder::new()
-                                .color(if (x + y) % 2 == 0 {
-                                    [0.0, 0.1, 0.0, 1.0]
-                                } else {
-                                    [0.1, 0.0, 0.0, 1.0]
-                                })
-                                .build()
-                        })
-                        .collect(),
-                )
+                .children((0..50).map(|x| checker_slab(x + y, even_color)).collect())
                 .build(),
             BoxLayoutBuilder::new()
                 .child_direction(ChildDirection::Horizontal)
-                .children(
-                    (0..50)
-                        .map(|x| {
-                            SlabBuilder::new()
-                                .color(if (x + y) % 2 == 0 {
-                                    [0.0, 0.1, 0.0, 1.0]
-                                } else {
-                                    [0.1, 0.0, 0.0, 1.0]
-                                })
-                                .build()
-                        })
-                        .collect(),
-                )
+                .children((0..50).map(|x| checker_slab(x + y, even_color)).collect())
                 .build(),
         ])
         .build()
 }
+
+/// A slab of the checkerboard, following `even_color` on the even squares
+fn checker_slab(square: i32, even_color: &Mutable<[f32; 4]>) -> Arc<dyn Widget> {
+    if square % 2 == 0 {
+        SlabBuilder::new()
+            .color_signal(clone!(even_color, move || even_color.signal()))
+            .build()
+    } else {
+        SlabBuilder::new().color([0.1, 0.0, 0.0, 1.0]).build()
+    }
+}
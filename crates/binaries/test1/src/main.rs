@@ -1,6 +1,6 @@
 use futures::stream::StreamExt;
 use futures_signals::signal::{Mutable, SignalExt};
-use glam::UVec2;
+use glam::Vec2;
 use glyphon::cosmic_text::Align;
 use glyphon::{FamilyOwned, Metrics};
 use inotify::WatchMask;
@@ -8,7 +8,7 @@ use lipsum::lipsum_words;
 use quirky::clone;
 use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
 use quirky::widget::{SizeConstraint, Widget};
-use quirky::widgets::events::{MouseEvent, WidgetEvent};
+use quirky::widgets::events::{KeyCode, MouseEvent, WidgetEvent};
 use quirky_widgets::components::text_input::text_input;
 use quirky_widgets::layouts::anchored_container::AnchoredContainerBuilder;
 use quirky_widgets::layouts::box_layout::{BoxLayoutBuilder, ChildDirection};
@@ -28,14 +28,9 @@ use wgpu::TextureFormat;
 async fn main() {
     let (quirky_winit_app, quirky_app) = QuirkyWinitApp::new(
         |resources: &mut QuirkyResources,
-         context: &QuirkyAppContext,
-         surface_format: TextureFormat| {
-            quirky_widgets::init(
-                resources,
-                context,
-                surface_format,
-                Some(QuirkyTheme::dark_default()),
-            );
+         _context: &QuirkyAppContext,
+         _surface_format: TextureFormat| {
+            quirky_widgets::init(resources, Some(QuirkyTheme::dark_default()));
 
             let mutable_theme = resources
                 .get_resource::<Mutable<QuirkyTheme>>()
@@ -78,6 +73,7 @@ async fn main() {
     .await
     .unwrap();
 
+    let quirky_winit_app = quirky_winit_app.with_debug_overlay_key(Some(KeyCode::F12));
     let draw_notifier = quirky_winit_app.get_trigger_draw_callback();
 
     tokio::spawn(quirky_app.run(draw_notifier));
@@ -86,7 +82,7 @@ async fn main() {
 
 fn button_row(text: Mutable<String>, theme: Mutable<QuirkyTheme>) -> Arc<dyn Widget> {
     BoxLayoutBuilder::new()
-        .size_constraint(SizeConstraint::MaxHeight(30))
+        .size_constraint(SizeConstraint::MaxHeight(30.0))
         .child_direction(ChildDirection::Horizontal)
         .children(vec![
             ButtonBuilder::new()
@@ -166,7 +162,7 @@ fn simple_panel_layout(resources: Arc<Mutex<QuirkyResources>>) -> Arc<dyn Widget
                         let children: Mutable<Vec<Arc<dyn Widget>>> = Mutable::new(vec![
                             BoxLayoutBuilder::new()
                                 .children(vec![SlabBuilder::new().build()])
-                                .size_constraint(SizeConstraint::MaxHeight(150))
+                                .size_constraint(SizeConstraint::MaxHeight(150.0))
                                 .build(),
                             BoxLayoutBuilder::new()
                                 .children(vec![
@@ -221,7 +217,7 @@ fn simple_panel_layout(resources: Arc<Mutex<QuirkyResources>>) -> Arc<dyn Widget
                                                 .text(lipsum_words(400).into())
                                                 .build(),
                                         ])
-                                        .size_constraint(SizeConstraint::MaxWidth(300))
+                                        .size_constraint(SizeConstraint::MaxWidth(300.0))
                                         .build(),
                                     BoxLayoutBuilder::new()
                                         .child_direction(ChildDirection::Vertical)
@@ -229,7 +225,7 @@ fn simple_panel_layout(resources: Arc<Mutex<QuirkyResources>>) -> Arc<dyn Widget
                                         .size_constraint(SizeConstraint::Unconstrained)
                                         .build(),
                                 ])
-                                .size_constraint(SizeConstraint::MinSize(UVec2::new(1, 150)))
+                                .size_constraint(SizeConstraint::MinSize(Vec2::new(1.0, 150.0)))
                                 .child_direction(ChildDirection::Horizontal)
                                 .build(),
                         ]);
@@ -799,6 +799,7 @@ impl BuilderStruct {
                         incoming_value,
                     );
                 let ctx = &*ctx;
+                self.invalidate();
                 #repaint
 
                 async move {
@@ -823,6 +824,7 @@ impl BuilderStruct {
             (self.#sig_name)().for_each(|incoming_value| {
                 self.#sig_propname.set(Some(incoming_value));
                 let ctx = &*ctx;
+                self.invalidate();
                 #repaint
 
                 async move {
@@ -878,8 +880,10 @@ impl Into<proc_macro::TokenStream> for BuilderStruct {
                 let out = #struct_name {
                     id: uuid::Uuid::new_v4(),
                     bounding_box: Default::default(),
+                    transform: Default::default(),
                     dirty: Default::default(),
-                    cached_primitives: Default::default(),
+                    cached_primitive_count: Default::default(),
+                    invalidations: Default::default(),
                     #(#real_struct_member_inits),*
                 }.build();
 
@@ -891,8 +895,10 @@ impl Into<proc_macro::TokenStream> for BuilderStruct {
         pub struct #struct_name<#(#builder_struct_generics_params),*> {
             id: uuid::Uuid,
             bounding_box: futures_signals::signal::Mutable<quirky::LayoutBox>,
+            transform: futures_signals::signal::Mutable<Option<glam::Affine2>>,
             dirty: futures_signals::signal::Mutable<bool>,
-            cached_primitives: futures_signals::signal::Mutable<Option<Vec<Box<dyn quirky::drawable_primitive::DrawablePrimitive>>>>,
+            cached_primitive_count: futures_signals::signal::Mutable<usize>,
+            invalidations: futures_signals::signal::Mutable<Option<quirky::drawable_tree::Invalidations>>,
             #(#real_struct_members),*
         }
 
@@ -905,32 +911,63 @@ impl Into<proc_macro::TokenStream> for BuilderStruct {
                 self.id
             }
 
+            fn type_name(&self) -> &'static str {
+                stringify!(#struct_name)
+            }
+
              fn set_bounding_box(&self, new_box: quirky::LayoutBox) {
+                let changed = self.bounding_box.get() != new_box;
                 self.bounding_box.set(new_box);
+
+                if changed {
+                    self.invalidate();
+                }
             }
 
             fn bounding_box(&self) -> futures_signals::signal::ReadOnlyMutable<quirky::LayoutBox> {
                 self.bounding_box.read_only()
             }
 
+            fn transform(&self) -> futures_signals::signal::ReadOnlyMutable<Option<glam::Affine2>> {
+                self.transform.read_only()
+            }
+
+            fn set_transform(&self, transform: Option<glam::Affine2>) {
+                if self.transform.get() != transform {
+                    self.transform.set(transform);
+                    self.set_dirty();
+                }
+            }
+
             fn dirty(&self)  -> futures_signals::signal::ReadOnlyMutable<bool> {
                 self.dirty.read_only()
             }
 
             fn set_dirty(&self) -> () {
                 self.dirty.set(true);
+                self.invalidate();
             }
 
             fn clear_dirty(&self) -> () {
                 self.dirty.set(false);
             }
 
-            fn get_cached_primitives(&self) -> Option<Vec<Box<dyn quirky::drawable_primitive::DrawablePrimitive>>> {
-                self.cached_primitives.lock_mut().take()
+            fn cached_primitive_count(&self) -> usize {
+                self.cached_primitive_count.get()
+            }
+
+            fn set_cached_primitive_count(&self, count: usize) {
+                self.cached_primitive_count.set(count);
             }
 
-            fn set_cached_primitives(&self, primitives: Option<Vec<Box<dyn quirky::drawable_primitive::DrawablePrimitive>>>) -> () {
-                let _ = self.cached_primitives.lock_mut().insert(primitives.or(Some(vec![])).unwrap());
+            fn set_invalidations(&self, invalidations: quirky::drawable_tree::Invalidations) {
+                self.invalidations.set(Some(invalidations));
+            }
+
+            fn invalidate(&self) {
+                if let Some(invalidations) = self.invalidations.lock_ref().as_ref() {
+                    invalidations.invalidate(self.id);
+                }
             }
 
             #props_runner
@@ -0,0 +1,476 @@
+use anyhow::anyhow;
+use glam::{vec2, Affine2, UVec2, Vec2};
+use image::{Rgba, RgbaImage};
+use quirky::display_list::{collect_display_list, linear_to_srgb, DisplayItem, DisplayList};
+use quirky::opacity_groups::{self, OpacityGroup};
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
+use quirky::widget::Widget;
+use quirky::{LayoutBox, QuirkyApp};
+use std::sync::Arc;
+use tiny_skia::{
+    BlendMode, Color, ColorU8, FillRule, FilterQuality, Mask, Paint, PathBuilder, Pixmap,
+    PixmapPaint, PremultipliedColorU8, Rect, Transform,
+};
+
+/// How the items are drawn, as set by the transform and clip items before them
+#[derive(Default)]
+struct Placement {
+    transform: Transform,
+    mask: Option<Mask>,
+}
+
+/// Renders the current widget tree of an app on the cpu.
+pub fn render_app(app: &QuirkyApp, size: UVec2) -> anyhow::Result<RgbaImage> {
+    render_display_list(&app.display_list(), size)
+}
+
+/// Renders a widget tree on the cpu, without any graphics device.
+pub fn render_widget_tree(
+    widget: &Arc<dyn Widget>,
+    ctx: &QuirkyAppContext,
+    resources: &mut QuirkyResources,
+    size: UVec2,
+) -> anyhow::Result<RgbaImage> {
+    let mut display_list = DisplayList::default();
+
+    collect_display_list(widget, ctx, resources, &mut display_list);
+
+    render_display_list(&display_list, size)
+}
+
+/// Rasterizes a display list the way the wgpu backend draws it onto an srgb target.
+///
+/// Blending happens on the encoded values rather than in linear space, so translucent
+/// primitives and opacity groups can differ slightly from the gpu output.
+pub fn render_display_list(display_list: &DisplayList, size: UVec2) -> anyhow::Result<RgbaImage> {
+    let new_pixmap = || {
+        Pixmap::new(size.x, size.y)
+            .ok_or_else(|| anyhow!("cannot render to an empty image of size {size}"))
+    };
+
+    let mut pixmap = new_pixmap()?;
+    let mut groups: Vec<(Pixmap, OpacityGroup, LayoutBox)> = vec![];
+    let mut placement = Placement::default();
+
+    pixmap.fill(Color::BLACK);
+
+    for item in display_list.items() {
+        let target = match groups.last_mut() {
+            Some((group_pixmap, _, _)) => group_pixmap,
+            None => &mut pixmap,
+        };
+
+        match item {
+            DisplayItem::Quad { pos, size, color } => {
+                fill_rect(target, *pos, *size, encode_color(color), &placement)
+            }
+            DisplayItem::Border {
+                pos,
+                size,
+                thickness,
+                color,
+            } => draw_border(
+                target,
+                *pos,
+                *size,
+                *thickness,
+                encode_color(color),
+                &placement,
+            ),
+            DisplayItem::Image {
+                pos,
+                size,
+                image,
+                srgb,
+            } => draw_image(target, *pos, *size, image, *srgb, &placement),
+            DisplayItem::Spans { clip, spans } => {
+                let clip_min = clip.pos.as_ivec2();
+                let clip_max = (clip.pos + clip.size).as_ivec2();
+
+                for span in spans {
+                    let min = span.pos.max(clip_min);
+                    let max = (span.pos + span.size.as_ivec2()).min(clip_max);
+
+                    if min.x < max.x && min.y < max.y {
+                        fill_rect(
+                            target,
+                            min.as_vec2(),
+                            (max - min).as_vec2(),
+                            span.color,
+                            &placement,
+                        );
+                    }
+                }
+            }
+            DisplayItem::Transform(affine) => placement.transform = to_skia_transform(affine),
+            DisplayItem::Clip(clip) => {
+                placement.mask = clip.and_then(|clip| clip_mask(&clip, size));
+            }
+            DisplayItem::PushGroup { group, clip } => {
+                groups.push((new_pixmap()?, *group, *clip));
+            }
+            DisplayItem::PopGroup => {
+                let (mut group_pixmap, group, clip) = groups
+                    .pop()
+                    .ok_or_else(|| anyhow!("display list pops a group it never pushed"))?;
+                let target = match groups.last_mut() {
+                    Some((group_pixmap, _, _)) => group_pixmap,
+                    None => &mut pixmap,
+                };
+
+                composite_group(target, &mut group_pixmap, &group, clip);
+            }
+        }
+    }
+
+    let mut image = RgbaImage::new(size.x, size.y);
+
+    for (dst, src) in image.pixels_mut().zip(pixmap.pixels()) {
+        let color = src.demultiply();
+        *dst = Rgba([color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+
+    Ok(image)
+}
+
+fn encode_color(color: &[f32; 4]) -> [u8; 4] {
+    [
+        linear_to_srgb(color[0]),
+        linear_to_srgb(color[1]),
+        linear_to_srgb(color[2]),
+        (color[3].clamp(0.0, 1.0) * 255.0).round() as u8,
+    ]
+}
+
+/// A mask covering the pixels inside of `clip`
+fn clip_mask(clip: &LayoutBox, size: UVec2) -> Option<Mask> {
+    let mut mask = Mask::new(size.x, size.y)?;
+
+    if let Some(rect) = Rect::from_xywh(clip.pos.x, clip.pos.y, clip.size.x, clip.size.y) {
+        mask.fill_path(
+            &PathBuilder::from_rect(rect),
+            FillRule::Winding,
+            false,
+            Transform::identity(),
+        );
+    }
+
+    Some(mask)
+}
+
+fn to_skia_transform(affine: &Affine2) -> Transform {
+    Transform::from_row(
+        affine.matrix2.x_axis.x,
+        affine.matrix2.x_axis.y,
+        affine.matrix2.y_axis.x,
+        affine.matrix2.y_axis.y,
+        affine.translation.x,
+        affine.translation.y,
+    )
+}
+
+/// Composites a group layer the way [`OpacityGroup`] does on the gpu, clearing it first outside
+/// of the area the group is clipped to
+fn composite_group(target: &mut Pixmap, layer: &mut Pixmap, group: &OpacityGroup, clip: LayoutBox) {
+    let width = layer.width() as usize;
+    let clip_min = clip.pos.floor().as_uvec2();
+    let clip_max = (clip.pos + clip.size).ceil().as_uvec2();
+
+    for (index, pixel) in layer.pixels_mut().iter_mut().enumerate() {
+        let (x, y) = ((index % width) as u32, (index / width) as u32);
+
+        if x < clip_min.x || y < clip_min.y || x >= clip_max.x || y >= clip_max.y {
+            *pixel = PremultipliedColorU8::TRANSPARENT;
+        }
+    }
+
+    let blend_mode = match group.blend_mode {
+        opacity_groups::BlendMode::Normal => BlendMode::SourceOver,
+        opacity_groups::BlendMode::Additive => BlendMode::Plus,
+        opacity_groups::BlendMode::Multiply => BlendMode::Multiply,
+    };
+
+    target.draw_pixmap(
+        0,
+        0,
+        layer.as_ref(),
+        &PixmapPaint {
+            opacity: group.opacity.clamp(0.0, 1.0),
+            blend_mode,
+            quality: FilterQuality::Nearest,
+        },
+        Transform::identity(),
+        None,
+    );
+}
+
+fn fill_rect(pixmap: &mut Pixmap, pos: Vec2, size: Vec2, color: [u8; 4], placement: &Placement) {
+    if let Some(rect) = Rect::from_xywh(pos.x, pos.y, size.x, size.y) {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(color[0], color[1], color[2], color[3]);
+        paint.anti_alias = false;
+
+        pixmap.fill_rect(rect, &paint, placement.transform, placement.mask.as_ref());
+    }
+}
+
+fn draw_border(
+    pixmap: &mut Pixmap,
+    pos: Vec2,
+    size: Vec2,
+    thickness: f32,
+    color: [u8; 4],
+    placement: &Placement,
+) {
+    let thickness = thickness.min(size.x / 2.0).min(size.y / 2.0);
+    let inner_height = size.y - 2.0 * thickness;
+
+    fill_rect(pixmap, pos, vec2(size.x, thickness), color, placement);
+    fill_rect(
+        pixmap,
+        pos + vec2(0.0, size.y - thickness),
+        vec2(size.x, thickness),
+        color,
+        placement,
+    );
+    fill_rect(
+        pixmap,
+        pos + vec2(0.0, thickness),
+        vec2(thickness, inner_height),
+        color,
+        placement,
+    );
+    fill_rect(
+        pixmap,
+        pos + vec2(size.x - thickness, thickness),
+        vec2(thickness, inner_height),
+        color,
+        placement,
+    );
+}
+
+fn draw_image(
+    pixmap: &mut Pixmap,
+    pos: Vec2,
+    size: Vec2,
+    image: &RgbaImage,
+    srgb: bool,
+    placement: &Placement,
+) {
+    let Some(mut source) = Pixmap::new(image.width(), image.height()) else {
+        return;
+    };
+
+    let encode = |channel: u8| {
+        if srgb {
+            channel
+        } else {
+            linear_to_srgb(channel as f32 / 255.0)
+        }
+    };
+
+    for (dst, src) in source.pixels_mut().iter_mut().zip(image.pixels()) {
+        let [r, g, b, a] = src.0;
+        *dst = ColorU8::from_rgba(encode(r), encode(g), encode(b), a).premultiply();
+    }
+
+    let transform = placement.transform.pre_concat(Transform::from_row(
+        size.x / image.width() as f32,
+        0.0,
+        0.0,
+        size.y / image.height() as f32,
+        pos.x,
+        pos.y,
+    ));
+
+    pixmap.draw_pixmap(
+        0,
+        0,
+        source.as_ref(),
+        &PixmapPaint {
+            blend_mode: BlendMode::Source,
+            quality: FilterQuality::Nearest,
+            ..Default::default()
+        },
+        transform,
+        placement.mask.as_ref(),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{render_display_list, render_widget_tree};
+    use futures_signals::signal::Mutable;
+    use glam::{uvec2, vec2, Affine2};
+    use image::{Rgba, RgbaImage};
+    use quirky::display_list::{DisplayItem, DisplayList};
+    use quirky::opacity_groups::{BlendMode, OpacityGroup};
+    use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
+    use quirky::widget::{Widget, WidgetBase};
+    use quirky::{clone, LayoutBox};
+    use quirky_widgets::widgets::label::LabelBuilder;
+    use quirky_widgets::widgets::stack::StackBuilder;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    /// Lets the widget pick up its props before rendering it
+    async fn run_and_render(
+        widget: &Arc<dyn Widget>,
+        ctx: &QuirkyAppContext,
+        resources: &mut QuirkyResources,
+    ) -> RgbaImage {
+        tokio::select! {
+            _ = widget.clone().run(ctx) => unreachable!("widget run loop finished"),
+            _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+        }
+
+        render_widget_tree(widget, ctx, resources, uvec2(40, 40)).unwrap()
+    }
+
+    #[test]
+    fn renders_quads_and_borders() {
+        let mut display_list = DisplayList::default();
+
+        display_list.push(DisplayItem::Quad {
+            pos: vec2(2.0, 2.0),
+            size: vec2(4.0, 4.0),
+            color: [1.0, 0.0, 0.0, 1.0],
+        });
+
+        display_list.push(DisplayItem::Border {
+            pos: vec2(0.0, 0.0),
+            size: vec2(8.0, 8.0),
+            thickness: 1.0,
+            color: [0.0, 1.0, 0.0, 1.0],
+        });
+
+        let image = render_display_list(&display_list, uvec2(10, 10)).unwrap();
+
+        assert_eq!(image.get_pixel(3, 3), &Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 255, 0, 255]));
+        assert_eq!(image.get_pixel(7, 4), &Rgba([0, 255, 0, 255]));
+        assert_eq!(image.get_pixel(1, 1), &Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(9, 9), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn renders_transforms_and_opacity_groups() {
+        let mut display_list = DisplayList::default();
+
+        display_list.set_transform(Affine2::from_scale_angle_translation(
+            vec2(2.0, 2.0),
+            0.0,
+            vec2(10.0, 0.0),
+        ));
+        display_list.push(DisplayItem::Quad {
+            pos: vec2(0.0, 0.0),
+            size: vec2(2.0, 2.0),
+            color: [1.0, 0.0, 0.0, 1.0],
+        });
+
+        display_list.set_transform(Affine2::IDENTITY);
+        display_list.push(DisplayItem::PushGroup {
+            group: OpacityGroup {
+                opacity: 0.5,
+                blend_mode: BlendMode::Normal,
+            },
+            clip: LayoutBox {
+                pos: vec2(0.0, 0.0),
+                size: vec2(4.0, 4.0),
+            },
+        });
+        display_list.push(DisplayItem::Quad {
+            pos: vec2(0.0, 0.0),
+            size: vec2(8.0, 8.0),
+            color: [1.0, 1.0, 1.0, 1.0],
+        });
+        display_list.push(DisplayItem::PopGroup);
+
+        let image = render_display_list(&display_list, uvec2(16, 16)).unwrap();
+
+        assert_eq!(image.get_pixel(13, 3), &Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(14, 3), &Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(9, 0), &Rgba([0, 0, 0, 255]));
+
+        let Rgba([r, g, b, a]) = *image.get_pixel(2, 2);
+        assert!((127..=128).contains(&r) && r == g && g == b && a == 255);
+        assert_eq!(image.get_pixel(6, 6), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn clips_items_to_the_clip_rect() {
+        let mut display_list = DisplayList::default();
+
+        display_list.set_clip(Some(LayoutBox {
+            pos: vec2(2.0, 2.0),
+            size: vec2(4.0, 4.0),
+        }));
+        display_list.push(DisplayItem::Quad {
+            pos: vec2(0.0, 0.0),
+            size: vec2(8.0, 8.0),
+            color: [1.0, 0.0, 0.0, 1.0],
+        });
+
+        let image = render_display_list(&display_list, uvec2(8, 8)).unwrap();
+
+        assert_eq!(image.get_pixel(2, 2), &Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(5, 5), &Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(1, 1), &BLACK);
+        assert_eq!(image.get_pixel(6, 6), &BLACK);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a system font to draw the label with"]
+    async fn clipping_stacks_clip_their_labels() {
+        let (tx, _rx) = async_std::channel::unbounded();
+        let ctx = QuirkyAppContext::new(
+            Mutable::new(uvec2(40, 40)).read_only(),
+            tx,
+            Default::default(),
+        );
+        let mut resources = QuirkyResources::default();
+
+        quirky_widgets::init(&mut resources, None);
+
+        let clip = Mutable::new(false);
+        let label = LabelBuilder::new()
+            .text("WWWWWWWW".into())
+            .text_color([1.0, 1.0, 1.0, 1.0])
+            .build();
+        let stack = StackBuilder::new()
+            .children(vec![label.clone()])
+            .clip_signal(clone!(clip, move || clip.signal()))
+            .build();
+
+        let bb = LayoutBox {
+            pos: vec2(0.0, 0.0),
+            size: vec2(20.0, 20.0),
+        };
+
+        stack.set_bounding_box(bb);
+        label.set_bounding_box(bb);
+        // Draws the label at twice its size, spilling out of the stack
+        label.set_transform(Some(Affine2::from_scale(vec2(2.0, 2.0))));
+
+        let spilled = |image: &RgbaImage| {
+            image
+                .enumerate_pixels()
+                .any(|(x, y, pixel)| (x >= 20 || y >= 20) && *pixel != BLACK)
+        };
+
+        let unclipped = run_and_render(&stack, &ctx, &mut resources).await;
+
+        assert!(spilled(&unclipped));
+
+        clip.set(true);
+
+        let clipped = run_and_render(&stack, &ctx, &mut resources).await;
+
+        assert!(!spilled(&clipped));
+        assert!(clipped
+            .enumerate_pixels()
+            .any(|(x, y, pixel)| x < 20 && y < 20 && *pixel != BLACK));
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{anyhow, bail};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// Set this environment variable to overwrite the stored golden images with the current renders.
+pub const UPDATE_GOLDEN_ENV: &str = "QUIRKY_UPDATE_GOLDEN";
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// Largest per channel difference for a pixel to still be considered equal
+    pub per_channel: u8,
+    /// Number of pixels allowed to differ by more than `per_channel`
+    pub max_differing_pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            per_channel: 2,
+            max_differing_pixels: 0,
+        }
+    }
+}
+
+pub struct ImageComparison {
+    pub differing_pixels: usize,
+    pub max_channel_difference: u8,
+    pub diff_image: RgbaImage,
+}
+
+impl ImageComparison {
+    pub fn within(&self, tolerance: &Tolerance) -> bool {
+        self.differing_pixels <= tolerance.max_differing_pixels
+    }
+}
+
+/// Compares two images pixel by pixel.
+///
+/// The diff image shows the actual image dimmed, with every pixel differing by more than
+/// `per_channel` painted red.
+pub fn compare_images(
+    actual: &RgbaImage,
+    expected: &RgbaImage,
+    per_channel: u8,
+) -> anyhow::Result<ImageComparison> {
+    if actual.dimensions() != expected.dimensions() {
+        bail!(
+            "image size mismatch: actual {:?}, expected {:?}",
+            actual.dimensions(),
+            expected.dimensions()
+        );
+    }
+
+    let mut differing_pixels = 0;
+    let mut max_channel_difference = 0;
+    let mut diff_image = RgbaImage::new(actual.width(), actual.height());
+
+    for ((a, e), d) in actual
+        .pixels()
+        .zip(expected.pixels())
+        .zip(diff_image.pixels_mut())
+    {
+        let difference =
+            a.0.iter()
+                .zip(e.0.iter())
+                .map(|(a, e)| a.abs_diff(*e))
+                .max()
+                .unwrap_or(0);
+
+        max_channel_difference = max_channel_difference.max(difference);
+
+        *d = if difference > per_channel {
+            differing_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let luma = ((a.0[0] as u32 + a.0[1] as u32 + a.0[2] as u32) / 9) as u8;
+            Rgba([luma, luma, luma, 255])
+        };
+    }
+
+    Ok(ImageComparison {
+        differing_pixels,
+        max_channel_difference,
+        diff_image,
+    })
+}
+
+/// Compares `actual` to the golden PNG stored at `golden_path`.
+///
+/// On mismatch, `<name>.actual.png` and `<name>.diff.png` are written next to the golden image.
+/// A missing golden image is written from `actual`, but still fails the assertion unless
+/// [`UPDATE_GOLDEN_ENV`] is set, so new goldens have to be reviewed before they are committed.
+pub fn assert_matches_golden(
+    actual: &RgbaImage,
+    golden_path: impl AsRef<Path>,
+    tolerance: Tolerance,
+) -> anyhow::Result<()> {
+    let golden_path = golden_path.as_ref();
+    let update = std::env::var_os(UPDATE_GOLDEN_ENV).is_some();
+
+    if update || !golden_path.exists() {
+        if let Some(parent) = golden_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        actual.save(golden_path)?;
+
+        if update {
+            return Ok(());
+        }
+
+        bail!(
+            "golden image {} did not exist, wrote the current render in its place",
+            golden_path.display()
+        );
+    }
+
+    let expected = image::open(golden_path)?.to_rgba8();
+    let comparison = compare_images(actual, &expected, tolerance.per_channel)?;
+
+    if comparison.within(&tolerance) {
+        return Ok(());
+    }
+
+    let actual_path = sibling_path(golden_path, "actual")?;
+    let diff_path = sibling_path(golden_path, "diff")?;
+
+    actual.save(&actual_path)?;
+    comparison.diff_image.save(&diff_path)?;
+
+    bail!(
+        "{} pixels differ from {} (largest channel difference {}), see {}",
+        comparison.differing_pixels,
+        golden_path.display(),
+        comparison.max_channel_difference,
+        diff_path.display()
+    )
+}
+
+fn sibling_path(golden_path: &Path, suffix: &str) -> anyhow::Result<PathBuf> {
+    let stem = golden_path
+        .file_stem()
+        .ok_or_else(|| anyhow!("invalid golden path {}", golden_path.display()))?
+        .to_string_lossy();
+
+    Ok(golden_path.with_file_name(format!("{stem}.{suffix}.png")))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::golden::compare_images;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn compare_images_counts_pixels_outside_tolerance() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([10, 20, 30, 255]));
+        let mut actual = expected.clone();
+
+        actual.put_pixel(0, 0, Rgba([12, 20, 30, 255]));
+        actual.put_pixel(3, 3, Rgba([10, 90, 30, 255]));
+
+        let comparison = compare_images(&actual, &expected, 2).unwrap();
+
+        assert_eq!(comparison.differing_pixels, 1);
+        assert_eq!(comparison.max_channel_difference, 70);
+        assert_eq!(
+            comparison.diff_image.get_pixel(3, 3),
+            &Rgba([255, 0, 0, 255])
+        );
+        assert_ne!(
+            comparison.diff_image.get_pixel(0, 0),
+            &Rgba([255, 0, 0, 255])
+        );
+
+        assert!(compare_images(&RgbaImage::new(2, 2), &expected, 2).is_err());
+    }
+}
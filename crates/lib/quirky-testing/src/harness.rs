@@ -0,0 +1,107 @@
+use crate::golden::{assert_matches_golden, Tolerance};
+use async_std::task::sleep;
+use futures::future::{abortable, AbortHandle};
+use glam::UVec2;
+use image::RgbaImage;
+use quirky::headless::request_headless_device;
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
+use quirky::widget::Widget;
+use quirky::QuirkyApp;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wgpu::TextureFormat;
+
+const QUIET_PERIOD: Duration = Duration::from_millis(50);
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs a widget tree on a headless device, so it can be rendered and compared to golden images.
+///
+/// The app `run` future is driven on a background thread for as long as the harness lives.
+pub struct SnapshotHarness {
+    pub app: Arc<QuirkyApp>,
+    size: UVec2,
+    redraw_requests: Arc<AtomicUsize>,
+    abort_handle: AbortHandle,
+}
+
+impl SnapshotHarness {
+    pub async fn new(
+        size: UVec2,
+        init_fn: impl FnOnce(&mut QuirkyResources, &QuirkyAppContext, TextureFormat) -> (),
+        ui_factory: impl FnOnce(Arc<Mutex<QuirkyResources>>) -> Arc<dyn Widget>,
+    ) -> anyhow::Result<Self> {
+        let (device, queue) = request_headless_device().await?;
+
+        let app = Arc::new(QuirkyApp::new(
+            device,
+            queue,
+            TextureFormat::Rgba8UnormSrgb,
+            init_fn,
+            ui_factory,
+        ));
+
+        app.viewport_size.set(size);
+
+        let redraw_requests = Arc::new(AtomicUsize::new(0));
+        let counter = redraw_requests.clone();
+
+        let (run, abort_handle) = abortable(app.clone().run(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        std::thread::spawn(move || {
+            let _ = futures::executor::block_on(run);
+        });
+
+        Ok(Self {
+            app,
+            size,
+            redraw_requests,
+            abort_handle,
+        })
+    }
+
+    /// Renders until the widget tree stops requesting redraws and no widget is dirty.
+    pub async fn settle(&self) -> anyhow::Result<()> {
+        let deadline = Instant::now() + SETTLE_TIMEOUT;
+
+        loop {
+            let requests_before = self.redraw_requests.load(Ordering::SeqCst);
+
+            self.app.render_to_image(self.size)?;
+            sleep(QUIET_PERIOD).await;
+
+            if requests_before == self.redraw_requests.load(Ordering::SeqCst)
+                && !self.app.has_dirty_widgets()
+            {
+                return Ok(());
+            }
+
+            if Instant::now() > deadline {
+                anyhow::bail!("widget tree did not settle within {SETTLE_TIMEOUT:?}");
+            }
+        }
+    }
+
+    pub async fn snapshot(&self) -> anyhow::Result<RgbaImage> {
+        self.settle().await?;
+        self.app.render_to_image(self.size)
+    }
+
+    pub async fn assert_snapshot(
+        &self,
+        golden_path: impl AsRef<Path>,
+        tolerance: Tolerance,
+    ) -> anyhow::Result<()> {
+        let image = self.snapshot().await?;
+        assert_matches_golden(&image, golden_path, tolerance)
+    }
+}
+
+impl Drop for SnapshotHarness {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
@@ -0,0 +1,120 @@
+//! Golden image tests for the primitives of `quirky-widgets`.
+//!
+//! The tests need a wgpu adapter, so they are ignored by default and run with
+//! `cargo test -- --ignored`. Set `QUIRKY_UPDATE_GOLDEN=1` to render the golden images again
+//! after an intended change.
+
+use glyphon::cosmic_text::Align;
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
+use quirky::widget::Widget;
+use quirky_testing::golden::Tolerance;
+use quirky_testing::harness::SnapshotHarness;
+use quirky_widgets::layouts::box_layout::BoxLayoutBuilder;
+use quirky_widgets::styling::Padding;
+use quirky_widgets::theming::QuirkyTheme;
+use quirky_widgets::widgets::button::ButtonBuilder;
+use quirky_widgets::widgets::label::LabelBuilder;
+use quirky_widgets::widgets::slab::SlabBuilder;
+use quirky_widgets::widgets::text_input::TextInputBuilder;
+use std::sync::Arc;
+use wgpu::TextureFormat;
+
+const PADDING: Padding = Padding {
+    left: 10.0,
+    right: 10.0,
+    top: 10.0,
+    bottom: 10.0,
+};
+
+/// A harness drawing `content` inset by [`PADDING`]
+async fn harness(content: impl FnOnce() -> Arc<dyn Widget> + Send + 'static) -> SnapshotHarness {
+    SnapshotHarness::new(
+        glam::uvec2(120, 60),
+        |resources: &mut QuirkyResources, _: &QuirkyAppContext, _: TextureFormat| {
+            quirky_widgets::init(resources, Some(QuirkyTheme::dark_default()))
+        },
+        move |_| {
+            BoxLayoutBuilder::new()
+                .padding(PADDING)
+                .children(vec![content()])
+                .build()
+        },
+    )
+    .await
+    .unwrap()
+}
+
+fn golden(name: &str) -> String {
+    format!("{}/tests/golden/{name}.png", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[tokio::test]
+#[ignore = "needs a GPU adapter"]
+async fn quads_match_golden() {
+    let harness = harness(|| SlabBuilder::new().color([0.8, 0.2, 0.1, 1.0]).build()).await;
+
+    harness
+        .assert_snapshot(golden("quads"), Tolerance::default())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a GPU adapter"]
+async fn border_box_matches_golden() {
+    let harness = harness(|| {
+        TextInputBuilder::new()
+            .text_value(String::new())
+            .on_text_change(|_| {})
+            .on_focus_change(|_| {})
+            .on_submit(|_| {})
+            .build()
+    })
+    .await;
+
+    harness
+        .assert_snapshot(golden("border_box"), Tolerance::default())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a GPU adapter"]
+async fn button_primitive_matches_golden() {
+    let harness = harness(|| {
+        ButtonBuilder::new()
+            .content(SlabBuilder::new().color([0.0, 0.0, 0.0, 0.0]).build())
+            .on_click(|_| {})
+            .build()
+    })
+    .await;
+
+    harness
+        .assert_snapshot(golden("button_primitive"), Tolerance::default())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a GPU adapter"]
+async fn text_matches_golden() {
+    let harness = harness(|| {
+        LabelBuilder::new()
+            .text("Quirky".into())
+            .text_align(Align::Center)
+            .text_color([0.9, 0.9, 0.9, 1.0])
+            .build()
+    })
+    .await;
+
+    // Glyph rasterization differs slightly between adapters and font versions
+    let tolerance = Tolerance {
+        per_channel: 8,
+        max_differing_pixels: 16,
+    };
+
+    harness
+        .assert_snapshot(golden("text"), tolerance)
+        .await
+        .unwrap();
+}
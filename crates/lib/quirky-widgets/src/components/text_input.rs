@@ -98,10 +98,10 @@ pub fn text_input(
         AnchoredContainerBuilder::new()
             .anchor_point(AnchorPoint::TopLeft)
             .padding(Padding {
-                left: 2,
-                right: 0,
-                top: 1,
-                bottom: 0,
+                left: 2.0,
+                right: 0.0,
+                top: 1.0,
+                bottom: 0.0,
             })
             .child_signal(clone!(
                 value_bc,
@@ -146,10 +146,10 @@ pub fn text_input(
         AnchoredContainerBuilder::new()
             .anchor_point(AnchorPoint::CenterLeft)
             .padding(Padding {
-                left: 4,
-                right: 0,
-                top: 0,
-                bottom: 0,
+                left: 4.0,
+                right: 0.0,
+                top: 0.0,
+                bottom: 0.0,
             })
             .child(
                 LabelBuilder::new()
@@ -173,7 +173,7 @@ pub fn text_input(
     ]);
 
     StackBuilder::new()
-        .size_constraint(SizeConstraint::MaxHeight(40))
+        .size_constraint(SizeConstraint::MaxHeight(40.0))
         .children_signal_vec(clone!(children, move || children.signal_vec_cloned()))
         .build()
 }
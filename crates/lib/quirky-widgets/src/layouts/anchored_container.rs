@@ -4,15 +4,14 @@ use futures::FutureExt;
 use futures::StreamExt;
 use futures_signals::map_ref;
 use futures_signals::signal::{always, Signal, SignalExt};
-use glam::UVec2;
+use glam::Vec2;
 use quirky::quirky_app_context::QuirkyAppContext;
 use quirky::widget::WidgetBase;
-use quirky::widget::{SizeConstraint, Widget};
+use quirky::widget::{hit_test, SizeConstraint, Widget};
 use quirky::widgets::layout_helper::layout;
 use quirky::LayoutBox;
 use quirky_macros::widget;
 use quirky_utils::futures_map_poll::FuturesMapPoll;
-use std::cmp::min;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -38,6 +37,10 @@ pub struct AnchoredContainer {
     #[signal_prop]
     #[default(Default::default())]
     padding: Padding,
+    /// Clips the children to the bounding box, rather than letting them draw outside of it
+    #[signal_prop]
+    #[default(false)]
+    clip: bool,
 }
 
 #[async_trait]
@@ -48,6 +51,8 @@ impl<
         AnchorPointSignalFn: Fn() -> AnchorPointSignal + Send + Sync + 'static,
         PaddingSignal: futures_signals::signal::Signal<Item = Padding> + Send + Sync + Unpin + 'static,
         PaddingSignalFn: Fn() -> PaddingSignal + Send + Sync + 'static,
+        ClipSignal: futures_signals::signal::Signal<Item = bool> + Send + Sync + Unpin + 'static,
+        ClipSignalFn: Fn() -> ClipSignal + Send + Sync + 'static,
     > Widget
     for AnchoredContainer<
         ChildSignal,
@@ -56,6 +61,8 @@ impl<
         AnchorPointSignalFn,
         PaddingSignal,
         PaddingSignalFn,
+        ClipSignal,
+        ClipSignalFn,
     >
 {
     fn children(&self) -> Option<Vec<Arc<dyn Widget>>> {
@@ -84,10 +91,14 @@ impl<
         )
     }
 
-    fn get_widget_at(&self, pos: UVec2, path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    fn clip_rect(&self) -> Option<LayoutBox> {
+        (self.clip_prop_value.get() == Some(true)).then(|| self.bounding_box.get())
+    }
+
+    fn get_widget_at(&self, pos: Vec2, path: Vec<Uuid>) -> Option<Vec<Uuid>> {
         self.child_prop_value
             .get_cloned()
-            .map(|c| c.get_widget_at(pos, path))
+            .map(|c| hit_test(&c, pos, path))
             .flatten()
     }
 
@@ -167,13 +178,13 @@ pub fn single_child_layout_strategy(
 
     let child_constraints = child_constraints.first().unwrap();
 
-    let padding_total = UVec2::new(padding.left + padding.right, padding.top + padding.bottom);
+    let padding_total = Vec2::new(padding.left + padding.right, padding.top + padding.bottom);
 
     if container_box.size.x < padding_total.x || container_box.size.y < padding_total.y {
         return vec![];
     }
 
-    let top_left = container_box.pos + UVec2::new(padding.left, padding.top);
+    let top_left = container_box.pos + Vec2::new(padding.left, padding.top);
     let size = container_box.size - padding_total;
 
     let allowed_box = LayoutBox {
@@ -183,133 +194,38 @@ pub fn single_child_layout_strategy(
 
     let allocated_size = match child_constraints {
         SizeConstraint::Unconstrained => allowed_box.size,
-        SizeConstraint::MaxSize(max_size) => {
-            let size_x = min(allowed_box.size.x, max_size.x);
-            let size_y = min(allowed_box.size.y, max_size.y);
-
-            UVec2::new(size_x, size_y)
-        }
+        SizeConstraint::MaxSize(max_size) => allowed_box.size.min(*max_size),
         SizeConstraint::MinSize(_) => {
             // For single child layouts, MinSize equates to unconstrained as it only makes sense to
             // consume the allowed space
             allowed_box.size
         }
         SizeConstraint::MaxHeight(max_height) => {
-            let size_x = allowed_box.size.x;
-            let size_y = min(allowed_box.size.y, *max_height);
-
-            UVec2::new(size_x, size_y)
+            Vec2::new(allowed_box.size.x, allowed_box.size.y.min(*max_height))
         }
         SizeConstraint::MaxWidth(max_width) => {
-            let size_x = min(allowed_box.size.x, *max_width);
-            let size_y = allowed_box.size.y;
-
-            UVec2::new(size_x, size_y)
+            Vec2::new(allowed_box.size.x.min(*max_width), allowed_box.size.y)
         }
     };
 
-    let output_pos = match anchor_point {
-        AnchorPoint::Center => {
-            let allowed_center_x = allowed_box.size.x / 2;
-            let allowed_center_y = allowed_box.size.y / 2;
-
-            let allocated_half_x = allocated_size.x / 2;
-            let allocated_half_y = allocated_size.y / 2;
-
-            let pos_x = if allocated_half_x <= allowed_center_x {
-                allowed_center_x - allocated_half_x
-            } else {
-                0
-            };
-
-            let pos_y = if allocated_half_y <= allowed_center_y {
-                allowed_center_y - allocated_half_y
-            } else {
-                0
-            };
-
-            top_left + UVec2::new(pos_x, pos_y)
-        }
-        AnchorPoint::TopLeft => top_left,
-        AnchorPoint::TopCenter => {
-            let allowed_center_x = allowed_box.size.x / 2;
-            let allocated_half_x = allocated_size.x / 2;
-
-            let pos_x = if allocated_half_x <= allowed_center_x {
-                allowed_center_x - allocated_half_x
-            } else {
-                0
-            };
-
-            top_left + UVec2::new(pos_x, 0)
-        }
-        AnchorPoint::TopRight => {
-            let pos_x = allowed_box.size.x - allocated_size.x;
-
-            top_left + UVec2::new(pos_x, 0)
-        }
-        AnchorPoint::CenterLeft => {
-            let allowed_center_y = allowed_box.size.y / 2;
-            let allocated_half_y = allocated_size.y / 2;
-
-            let pos_y = if allocated_half_y <= allowed_center_y {
-                allowed_center_y - allocated_half_y
-            } else {
-                0
-            };
-
-            top_left + UVec2::new(0, pos_y)
-        }
-        AnchorPoint::CenterRight => {
-            let allowed_center_x = allowed_box.size.x;
-            let allocated_half_x = allocated_size.x;
-
-            let pos_x = if allocated_half_x <= allowed_center_x {
-                allowed_center_x - allocated_half_x
-            } else {
-                0
-            };
-
-            let allowed_center_y = allowed_box.size.y / 2;
-            let allocated_half_y = allocated_size.y / 2;
-
-            let pos_y = if allocated_half_y <= allowed_center_y {
-                allowed_center_y - allocated_half_y
-            } else {
-                0
-            };
-
-            top_left + UVec2::new(pos_x, pos_y)
-        }
-        AnchorPoint::BottomLeft => {
-            let pos_y = allowed_box.size.y - allocated_size.y;
-
-            top_left + UVec2::new(0, pos_y)
-        }
-        AnchorPoint::BottomCenter => {
-            let allowed_center_x = allowed_box.size.x / 2;
-            let allocated_half_x = allocated_size.x / 2;
-
-            let pos_x = if allocated_half_x <= allowed_center_x {
-                allowed_center_x - allocated_half_x
-            } else {
-                0
-            };
-
-            let pos_y = allowed_box.size.y - allocated_size.y;
-
-            top_left + UVec2::new(pos_x, pos_y)
-        }
-        AnchorPoint::BottomRight => {
-            let pos_x = allowed_box.size.x - allocated_size.x;
-            let pos_y = allowed_box.size.y - allocated_size.y;
-
-            top_left + UVec2::new(pos_x, pos_y)
-        }
+    // The free space on each axis, split evenly when centering
+    let free = (allowed_box.size - allocated_size).max(Vec2::ZERO);
+    let center = free / 2.0;
+
+    let offset = match anchor_point {
+        AnchorPoint::TopLeft => Vec2::ZERO,
+        AnchorPoint::TopCenter => Vec2::new(center.x, 0.0),
+        AnchorPoint::TopRight => Vec2::new(free.x, 0.0),
+        AnchorPoint::CenterLeft => Vec2::new(0.0, center.y),
+        AnchorPoint::Center => center,
+        AnchorPoint::CenterRight => Vec2::new(free.x, center.y),
+        AnchorPoint::BottomLeft => Vec2::new(0.0, free.y),
+        AnchorPoint::BottomCenter => Vec2::new(center.x, free.y),
+        AnchorPoint::BottomRight => free,
     };
 
     vec![LayoutBox {
-        pos: output_pos,
+        pos: top_left + offset,
         size: allocated_size,
     }]
 }
@@ -6,9 +6,9 @@ use futures_signals::signal::Signal;
 use futures_signals::signal::SignalExt;
 use futures_signals::signal_vec::SignalVecExt;
 use futures_signals::signal_vec::VecDiff;
-use glam::UVec2;
+use glam::Vec2;
 use quirky::quirky_app_context::QuirkyAppContext;
-use quirky::widget::{SizeConstraint, Widget, WidgetBase};
+use quirky::widget::{hit_test, SizeConstraint, Widget, WidgetBase};
 use quirky::widgets::layout_helper::layout;
 use quirky::LayoutBox;
 use quirky_macros::widget;
@@ -35,6 +35,10 @@ pub struct BoxLayout {
     #[signal_prop]
     #[default(Default::default())]
     padding: Padding,
+    /// Clips the children to the bounding box, rather than letting them draw outside of it
+    #[signal_prop]
+    #[default(false)]
+    clip: bool,
 }
 
 #[async_trait]
@@ -45,6 +49,8 @@ impl<
         SizeConstraintSignalFn: Fn() -> SizeConstraintSignal + Send + Sync + 'static,
         PaddingSignal: futures_signals::signal::Signal<Item = Padding> + Send + Sync + Unpin + 'static,
         PaddingSignalFn: Fn() -> PaddingSignal + Send + Sync + 'static,
+        ClipSignal: futures_signals::signal::Signal<Item = bool> + Send + Sync + Unpin + 'static,
+        ClipSignalFn: Fn() -> ClipSignal + Send + Sync + 'static,
         ChildrenSignal: futures_signals::signal_vec::SignalVec<Item = Arc<dyn Widget>>
             + Send
             + Sync
@@ -59,6 +65,8 @@ impl<
         SizeConstraintSignalFn,
         PaddingSignal,
         PaddingSignalFn,
+        ClipSignal,
+        ClipSignalFn,
         ChildrenSignal,
         ChildrenSignalFn,
     >
@@ -71,7 +79,11 @@ impl<
         Box::new((self.size_constraint)())
     }
 
-    fn get_widget_at(&self, pos: UVec2, path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    fn clip_rect(&self) -> Option<LayoutBox> {
+        (self.clip_prop_value.get() == Some(true)).then(|| self.bounding_box.get())
+    }
+
+    fn get_widget_at(&self, pos: Vec2, path: Vec<Uuid>) -> Option<Vec<Uuid>> {
         let bb = self.bounding_box.get();
 
         if !bb.contains(pos) {
@@ -81,7 +93,7 @@ impl<
         let child_data = self.children_prop_value.lock_ref();
 
         for c in child_data.iter().rev() {
-            if let Some(mut out_path) = c.get_widget_at(pos, path.clone()) {
+            if let Some(mut out_path) = hit_test(c, pos, path.clone()) {
                 out_path.push(self.id);
                 return Some(out_path);
             }
@@ -159,6 +171,8 @@ impl<
         SizeConstraintSignalFn: Fn() -> SizeConstraintSignal + Send + Sync + 'static,
         PaddingSignal: futures_signals::signal::Signal<Item = Padding> + Send + Sync + Unpin + 'static,
         PaddingSignalFn: Fn() -> PaddingSignal + Send + Sync + 'static,
+        ClipSignal: futures_signals::signal::Signal<Item = bool> + Send + Sync + Unpin + 'static,
+        ClipSignalFn: Fn() -> ClipSignal + Send + Sync + 'static,
         ChildrenSignal: futures_signals::signal_vec::SignalVec<Item = Arc<dyn Widget>>
             + Send
             + Sync
@@ -173,6 +187,8 @@ impl<
         SizeConstraintSignalFn,
         PaddingSignal,
         PaddingSignalFn,
+        ClipSignal,
+        ClipSignalFn,
         ChildrenSignal,
         ChildrenSignalFn,
     >
@@ -195,13 +211,13 @@ fn box_layout_strategy(
     let direction = extras.0;
     let padding = extras.1;
 
-    let padding_total = UVec2::new(padding.left + padding.right, padding.top + padding.bottom);
+    let padding_total = Vec2::new(padding.left + padding.right, padding.top + padding.bottom);
 
     if container_box.size.x < padding_total.x || container_box.size.y < padding_total.y {
         return vec![];
     }
 
-    let top_left = container_box.pos + UVec2::new(padding.left, padding.top);
+    let top_left = container_box.pos + Vec2::new(padding.left, padding.top);
     let size = container_box.size - padding_total;
 
     let container_box = LayoutBox {
@@ -209,21 +225,21 @@ fn box_layout_strategy(
         size,
     };
 
-    let total_items = child_constraints.len().max(1) as u32;
+    let total_items = child_constraints.len().max(1) as f32;
 
-    let min_requirements_x: u32 = child_constraints
+    let min_requirements_x: f32 = child_constraints
         .iter()
         .map(|r| match r {
             SizeConstraint::MinSize(s) => s.x,
-            _ => 0,
+            _ => 0.0,
         })
         .sum();
 
-    let min_requirements_y: u32 = child_constraints
+    let min_requirements_y: f32 = child_constraints
         .iter()
         .map(|r| match r {
             SizeConstraint::MinSize(s) => s.y,
-            _ => 0,
+            _ => 0.0,
         })
         .sum();
 
@@ -235,14 +251,14 @@ fn box_layout_strategy(
         };
 
         let mut per_remaining_width_bonus = remaining_width / total_items;
-        let mut x_pos = 0;
+        let mut x_pos = 0.0;
 
         child_constraints
             .iter()
             .map(|i| {
                 let base_x = match i {
                     SizeConstraint::MinSize(s) => s.x,
-                    _ => 0,
+                    _ => 0.0,
                 };
 
                 let item_width = base_x + per_remaining_width_bonus;
@@ -251,7 +267,7 @@ fn box_layout_strategy(
                         per_remaining_width_bonus += item_width - wm;
                     }
 
-                    *wm.min(&item_width)
+                    wm.min(item_width)
                 } else {
                     item_width
                 };
@@ -260,8 +276,8 @@ fn box_layout_strategy(
                 x_pos += item_width;
 
                 LayoutBox {
-                    pos: UVec2::new(container_box.pos.x + pos, container_box.pos.y),
-                    size: UVec2::new(item_width, container_box.size.y),
+                    pos: Vec2::new(container_box.pos.x + pos, container_box.pos.y),
+                    size: Vec2::new(item_width, container_box.size.y),
                 }
             })
             .collect()
@@ -274,14 +290,14 @@ fn box_layout_strategy(
 
         let mut per_remaining_height_bonus = remaining_height / total_items;
 
-        let mut y_pos = 0;
+        let mut y_pos = 0.0;
 
         child_constraints
             .iter()
             .map(|i| {
                 let base_y = match i {
                     SizeConstraint::MinSize(s) => s.y,
-                    _ => 0,
+                    _ => 0.0,
                 };
 
                 let item_height = base_y + per_remaining_height_bonus;
@@ -290,7 +306,7 @@ fn box_layout_strategy(
                         per_remaining_height_bonus += item_height - hm;
                     }
 
-                    *hm.min(&item_height)
+                    hm.min(item_height)
                 } else {
                     item_height
                 };
@@ -299,8 +315,8 @@ fn box_layout_strategy(
                 y_pos += item_height;
 
                 LayoutBox {
-                    pos: UVec2::new(container_box.pos.x, container_box.pos.y + pos),
-                    size: UVec2::new(container_box.size.x, item_height),
+                    pos: Vec2::new(container_box.pos.x, container_box.pos.y + pos),
+                    size: Vec2::new(container_box.size.x, item_height),
                 }
             })
             .collect()
@@ -309,10 +325,13 @@ fn box_layout_strategy(
 
 #[cfg(test)]
 mod test {
-    use crate::layouts::box_layout::BoxLayoutBuilder;
+    use crate::layouts::box_layout::{box_layout_strategy, BoxLayoutBuilder, ChildDirection};
+    use crate::styling::Padding;
     use futures_signals::signal::Mutable;
+    use glam::vec2;
     use quirky::clone;
     use quirky::widget::SizeConstraint;
+    use quirky::LayoutBox;
 
     #[test]
     fn box_layout_usage() {
@@ -320,8 +339,28 @@ mod test {
 
         let _box_layout_props = BoxLayoutBuilder::new()
             .children(vec![])
-            .child_direction(super::ChildDirection::Vertical)
+            .child_direction(ChildDirection::Vertical)
             .size_constraint_signal(clone!(constraint, move || constraint.signal()))
             .build();
     }
+
+    #[test]
+    fn box_layout_splits_space_without_rounding() {
+        let container = LayoutBox {
+            pos: vec2(0.0, 10.0),
+            size: vec2(100.0, 20.0),
+        };
+
+        let layouts = box_layout_strategy(
+            &container,
+            &vec![SizeConstraint::Unconstrained; 3],
+            &(ChildDirection::Horizontal, Padding::default()),
+        );
+
+        let width = 100.0 / 3.0;
+
+        assert_eq!(layouts.len(), 3);
+        assert_eq!(layouts[1].pos, vec2(width, 10.0));
+        assert_eq!(layouts[2].size, vec2(width, 20.0));
+    }
 }
@@ -1,9 +1,12 @@
-use crate::resources::font_resource::FontResource;
+use crate::primitives::border_box::BorderBoxPipeline;
+use crate::primitives::button_primitive::ButtonPipeline;
+use crate::primitives::image::ImagePipeline;
+use crate::primitives::quad::QuadPipeline;
+use crate::resources::font_resource::{FontResource, FontSystemPool};
 use crate::theming::QuirkyTheme;
 use futures_signals::signal::Mutable;
-use glyphon::{FontSystem, SwashCache, TextAtlas};
-use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
-use wgpu::TextureFormat;
+use glyphon::{FontSystem, SwashCache};
+use quirky::quirky_app_context::QuirkyResources;
 
 pub mod components;
 pub mod layouts;
@@ -13,19 +16,28 @@ pub mod styling;
 pub mod theming;
 pub mod widgets;
 
-pub fn init(
-    resources: &mut QuirkyResources,
-    context: &QuirkyAppContext,
-    surface_format: TextureFormat,
-    theme: Option<QuirkyTheme>,
-) {
+pub fn init(resources: &mut QuirkyResources, theme: Option<QuirkyTheme>) {
+    let font_system = FontSystem::new();
+
+    resources.insert_shared(FontSystemPool::new(&font_system));
     resources.insert(FontResource {
-        font_system: FontSystem::new(),
+        font_system,
         font_cache: SwashCache::new(),
-        text_atlas: TextAtlas::new(&context.device, &context.queue, surface_format),
+        text_atlas: None,
     });
 
     let theme = theme.unwrap_or(QuirkyTheme::dark_default());
 
     resources.insert(Mutable::new(theme));
+
+    resources.register_pipeline::<QuadPipeline>();
+    resources.register_pipeline::<BorderBoxPipeline>();
+    resources.register_pipeline::<ButtonPipeline>();
+    resources.register_pipeline::<ImagePipeline>();
+
+    #[cfg(feature = "shader-hot-reload")]
+    resources.watch_shaders(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/primitives/shaders"
+    ));
 }
@@ -1,18 +1,19 @@
-use crate::primitives::vertex::{Vertex, QUAD_INDEXES, QUAD_VERTICES};
+use crate::primitives::snap_rect;
+use crate::primitives::vertex::{UnitQuadBuffers, Vertex};
 use futures_signals::signal::ReadOnlyMutable;
+use quirky::display_list::{DisplayItem, DisplayList};
 use quirky::drawable_primitive::DrawablePrimitive;
+use quirky::pipeline_registry::{PipelineContext, PipelineId, PipelineKind};
+use quirky::quirky_app_context::QuirkyResources;
 use quirky::render_contexts::{PrepareContext, RenderContext};
+use quirky::shader_source;
 use std::mem;
-use uuid::Uuid;
-use wgpu::util::DeviceExt;
+use std::sync::Arc;
 use wgpu::{
-    include_wgsl, Device, PipelineLayoutDescriptor, RenderPass, RenderPipeline,
-    RenderPipelineDescriptor, VertexState,
+    PipelineLayoutDescriptor, RenderPass, RenderPipeline, RenderPipelineDescriptor, VertexState,
 };
 use wgpu_macros::VertexLayout;
 
-const BORDER_BOX_PRIMITIVE_UUID: Uuid = Uuid::from_u128(0xe136d9b9_d64a_4932_8eb3_106b52e2537c);
-
 #[repr(C)]
 #[derive(VertexLayout, bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, Default)]
 #[layout(Instance)]
@@ -68,118 +69,115 @@ impl BorderBoxData {
 
 pub struct BorderBox {
     data: ReadOnlyMutable<BorderBoxData>,
-    index_buffer: wgpu::Buffer,
-    instance_buffer: wgpu::Buffer,
-    vertex_buffer: wgpu::Buffer,
+    instance_buffer: Option<Arc<wgpu::Buffer>>,
+    pipeline: Option<PipelineId>,
 }
 
 impl BorderBox {
-    pub fn new(data: ReadOnlyMutable<BorderBoxData>, device: &Device) -> Self {
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("BorderBox data vertex buffer"),
-            contents: bytemuck::cast_slice(&QUAD_VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&QUAD_INDEXES),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("BorderBox data buffer"),
-            contents: bytemuck::cast_slice(&[data.get()]),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-
+    pub fn new(data: ReadOnlyMutable<BorderBoxData>) -> Self {
         Self {
             data,
-            index_buffer,
-            instance_buffer,
-            vertex_buffer,
+            instance_buffer: None,
+            pipeline: None,
         }
     }
 }
 
 impl DrawablePrimitive for BorderBox {
     fn prepare(&mut self, prepare_context: &mut PrepareContext) -> () {
-        if !prepare_context
-            .pipeline_cache
-            .contains_key(&BORDER_BOX_PRIMITIVE_UUID)
-        {
-            prepare_context.pipeline_cache.insert(
-                BORDER_BOX_PRIMITIVE_UUID,
-                create_border_box_pipeline(prepare_context),
-            );
-        }
+        self.pipeline = Some(prepare_context.pipeline::<BorderBoxPipeline>());
+
+        UnitQuadBuffers::ensure(prepare_context);
+
+        let key = prepare_context.pool_key("instance");
+        let instance_buffer = prepare_context.gpu_pool.buffer(
+            prepare_context.device,
+            key,
+            "BorderBox data buffer",
+            mem::size_of::<BorderBoxData>() as u64,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        );
+
+        let mut data = self.data.get();
+        (data.pos, data.size) = snap_rect(data.pos, data.size, prepare_context.scale_factor);
+
+        prepare_context
+            .queue
+            .write_buffer(&instance_buffer, 0, bytemuck::cast_slice(&[data]));
 
-        prepare_context.queue.write_buffer(
-            &self.instance_buffer,
-            0,
-            bytemuck::cast_slice(&[self.data.get()]),
-        )
+        self.instance_buffer = Some(instance_buffer);
     }
 
-    fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, render_context: &RenderContext<'a>) {
-        let pipeline = render_context
-            .pipeline_cache
-            .get(&BORDER_BOX_PRIMITIVE_UUID)
-            .unwrap();
+    fn pipeline_id(&self) -> Option<PipelineId> {
+        self.pipeline
+    }
 
-        pass.set_pipeline(pipeline);
+    fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, render_context: &'a RenderContext<'a>) {
+        pass.set_pipeline(render_context.pipeline(self.pipeline.unwrap()));
         pass.set_bind_group(0, &render_context.camera_bind_group, &[]);
-        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        UnitQuadBuffers::get(render_context.resources).bind(pass);
+        pass.set_vertex_buffer(1, self.instance_buffer.as_ref().unwrap().slice(..));
         pass.draw_indexed(0..6, 0, 0..1);
     }
-}
 
-fn create_border_box_pipeline(render_context: &PrepareContext) -> RenderPipeline {
-    let pipeline_layout = render_context
-        .device
-        .create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[render_context.camera_bind_group_layout],
-            push_constant_ranges: &[],
+    fn describe(&self, _resources: &mut QuirkyResources, display_list: &mut DisplayList) {
+        let data = self.data.get();
+
+        display_list.push(DisplayItem::Border {
+            pos: data.pos.into(),
+            size: data.size.into(),
+            thickness: 1.0,
+            color: [data.color[0], data.color[1], data.color[2], 1.0],
         });
+    }
+}
+
+/// The pipeline drawing [`BorderBox`]
+pub struct BorderBoxPipeline;
 
-    let shader = render_context
-        .device
-        .create_shader_module(include_wgsl!("shaders/border_box.wgsl"));
-
-    render_context
-        .device
-        .create_render_pipeline(&RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::LAYOUT, BorderBoxData::layout()],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: Some(wgpu::Face::Back),
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: render_context.surface_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        })
+impl PipelineKind for BorderBoxPipeline {
+    fn create(ctx: &PipelineContext) -> RenderPipeline {
+        let pipeline_layout = ctx
+            .device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[ctx.camera_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let shader = shader_source!("src/primitives/shaders/border_box.wgsl").create_module(ctx);
+
+        ctx.device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::LAYOUT, BorderBoxData::layout()],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: ctx.target.sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: ctx.target.format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+    }
 }
@@ -1,18 +1,18 @@
-use crate::primitives::vertex::{Vertex, QUAD_INDEXES, QUAD_VERTICES};
+use crate::primitives::snap_rect;
+use crate::primitives::vertex::{UnitQuadBuffers, Vertex};
 use futures_signals::signal::ReadOnlyMutable;
+use image::{Rgba, RgbaImage};
+use quirky::display_list::{linear_to_srgb, DisplayItem, DisplayList};
 use quirky::drawable_primitive::DrawablePrimitive;
+use quirky::pipeline_registry::{PipelineContext, PipelineId, PipelineKind};
+use quirky::quirky_app_context::QuirkyResources;
 use quirky::render_contexts::{PrepareContext, RenderContext};
+use quirky::shader_source;
 use std::mem;
-use uuid::Uuid;
-use wgpu::util::DeviceExt;
-use wgpu::{
-    include_wgsl, Device, PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    VertexState,
-};
+use std::sync::Arc;
+use wgpu::{PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor, VertexState};
 use wgpu_macros::VertexLayout;
 
-static BUTTON_PRIMITIVE_UUID: Uuid = Uuid::from_u128(0x2310e3e8_eda0_4321_96d5_44b4f42c24b0);
-
 #[repr(C)]
 #[derive(VertexLayout, bytemuck::Pod, bytemuck::Zeroable, Copy, Clone)]
 #[layout(Instance)]
@@ -60,118 +60,139 @@ impl ButtonData {
 
 pub struct ButtonPrimitive {
     button_data: ReadOnlyMutable<ButtonData>,
-    index_buffer: wgpu::Buffer,
-    instance_buffer: wgpu::Buffer,
-    vertex_buffer: wgpu::Buffer,
+    instance_buffer: Option<Arc<wgpu::Buffer>>,
+    pipeline: Option<PipelineId>,
 }
 
 impl ButtonPrimitive {
-    pub fn new(button_data: ReadOnlyMutable<ButtonData>, device: &Device) -> Self {
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("button data vertex buffer"),
-            contents: bytemuck::cast_slice(&QUAD_VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&QUAD_INDEXES),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("button data buffer"),
-            contents: bytemuck::cast_slice(&[button_data.get()]),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-
+    pub fn new(button_data: ReadOnlyMutable<ButtonData>) -> Self {
         Self {
             button_data,
-            index_buffer,
-            instance_buffer,
-            vertex_buffer,
+            instance_buffer: None,
+            pipeline: None,
         }
     }
 }
 
 impl DrawablePrimitive for ButtonPrimitive {
     fn prepare(&mut self, render_context: &mut PrepareContext) -> () {
-        if !render_context
-            .pipeline_cache
-            .contains_key(&BUTTON_PRIMITIVE_UUID)
-        {
-            render_context.pipeline_cache.insert(
-                BUTTON_PRIMITIVE_UUID,
-                create_button_pipeline(render_context),
-            );
-        }
+        self.pipeline = Some(render_context.pipeline::<ButtonPipeline>());
+
+        UnitQuadBuffers::ensure(render_context);
+
+        let key = render_context.pool_key("instance");
+        let instance_buffer = render_context.gpu_pool.buffer(
+            render_context.device,
+            key,
+            "button data buffer",
+            mem::size_of::<ButtonData>() as u64,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        );
 
-        render_context.queue.write_buffer(
-            &self.instance_buffer,
-            0,
-            bytemuck::cast_slice(&[self.button_data.get()]),
-        )
+        let mut data = self.button_data.get();
+        (data.pos, data.size) = snap_rect(data.pos, data.size, render_context.scale_factor);
+
+        render_context
+            .queue
+            .write_buffer(&instance_buffer, 0, bytemuck::cast_slice(&[data]));
+
+        self.instance_buffer = Some(instance_buffer);
     }
 
-    fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, render_context: &RenderContext<'a>) {
-        let pipeline = render_context
-            .pipeline_cache
-            .get(&BUTTON_PRIMITIVE_UUID)
-            .unwrap();
+    fn pipeline_id(&self) -> Option<PipelineId> {
+        self.pipeline
+    }
 
-        pass.set_pipeline(pipeline);
+    fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, render_context: &'a RenderContext<'a>) {
+        pass.set_pipeline(render_context.pipeline(self.pipeline.unwrap()));
         pass.set_bind_group(0, &render_context.camera_bind_group, &[]);
-        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        UnitQuadBuffers::get(render_context.resources).bind(pass);
+        pass.set_vertex_buffer(1, self.instance_buffer.as_ref().unwrap().slice(..));
         pass.draw_indexed(0..6, 0, 0..1);
     }
-}
 
-fn create_button_pipeline(render_context: &PrepareContext) -> RenderPipeline {
-    let pipeline_layout = render_context
-        .device
-        .create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[render_context.camera_bind_group_layout],
-            push_constant_ranges: &[],
+    /// Evaluates the button shader on the cpu, since the edge shading has no display item of its own
+    fn describe(&self, _resources: &mut QuirkyResources, display_list: &mut DisplayList) {
+        let data = self.button_data.get();
+        let width = data.size[0].max(0.0) as u32;
+        let height = data.size[1].max(0.0) as u32;
+
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            let centered_x = (x as f32 + 0.5) / width as f32 - 0.5;
+            let centered_y = (y as f32 + 0.5) / height as f32 - 0.5;
+
+            let closeness_to_edge_x = (centered_x.abs() * 2.0).powi(2);
+            let closeness_to_edge_y = (centered_y.abs() * 2.0).powi(2);
+            let distance = closeness_to_edge_x.max(closeness_to_edge_y);
+            let factor = 1.0 - (distance - 0.6).max(0.0);
+
+            Rgba([
+                linear_to_srgb(data.color[0] * factor),
+                linear_to_srgb(data.color[1] * factor),
+                linear_to_srgb(data.color[2] * factor),
+                255,
+            ])
+        });
+
+        display_list.push(DisplayItem::Image {
+            pos: data.pos.into(),
+            size: data.size.into(),
+            image: Arc::new(image),
+            srgb: true,
         });
+    }
+}
 
-    let shader = render_context
-        .device
-        .create_shader_module(include_wgsl!("shaders/button.wgsl"));
-
-    render_context
-        .device
-        .create_render_pipeline(&RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::LAYOUT, ButtonData::layout()],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: Some(wgpu::Face::Back),
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: render_context.surface_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        })
+/// The pipeline drawing [`ButtonPrimitive`]
+pub struct ButtonPipeline;
+
+impl PipelineKind for ButtonPipeline {
+    fn create(ctx: &PipelineContext) -> RenderPipeline {
+        let pipeline_layout = ctx
+            .device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[ctx.camera_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let shader = shader_source!("src/primitives/shaders/button.wgsl").create_module(ctx);
+
+        ctx.device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::LAYOUT, ButtonData::layout()],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: ctx.target.sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: ctx.target.format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+    }
 }
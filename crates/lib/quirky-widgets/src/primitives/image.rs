@@ -1,17 +1,21 @@
-use crate::primitives::vertex::{Vertex, QUAD_INDEXES, QUAD_VERTICES};
+use crate::primitives::vertex::{UnitQuadBuffers, Vertex};
 use image::RgbaImage;
+use quirky::display_list::{DisplayItem, DisplayList};
 use quirky::drawable_primitive::DrawablePrimitive;
+use quirky::pipeline_registry::{PipelineContext, PipelineId, PipelineKind};
+use quirky::quirky_app_context::QuirkyResources;
 use quirky::render_contexts::{PrepareContext, RenderContext};
+use quirky::shader_source;
 use quirky::LayoutBox;
 use std::mem;
+use std::sync::Arc;
 use uuid::Uuid;
-use wgpu::util::DeviceExt;
 use wgpu::{
-    include_wgsl, BindGroupLayout, Buffer, RenderPass, TextureDimension, TextureFormat, VertexState,
+    BindGroupLayout, Buffer, Device, RenderPass, RenderPipeline, TextureFormat, VertexState,
 };
 use wgpu_macros::VertexLayout;
 
-static PRIMITIVE_UUID: Uuid = Uuid::from_u128(0x96f1543e_52e7_4f6b_8dc9_c5561df1f404);
+static BIND_GROUP_UUID: Uuid = Uuid::from_u128(0x96f1543e_52e7_4f6b_8dc9_c5561df1f404);
 
 #[repr(C)]
 #[derive(VertexLayout, bytemuck::Pod, bytemuck::Zeroable, Copy, Clone)]
@@ -50,10 +54,20 @@ impl Quad {
 
 pub struct ImagePrimitive {
     pub data: RgbaImage,
-    pub vertex_buffer: Option<Buffer>,
-    pub index_buffer: Option<Buffer>,
-    pub instance_buffer: Option<Buffer>,
     pub bb: LayoutBox,
+    instance_buffer: Option<Arc<Buffer>>,
+    pipeline: Option<PipelineId>,
+}
+
+impl ImagePrimitive {
+    pub fn new(data: RgbaImage, bb: LayoutBox) -> Self {
+        Self {
+            data,
+            bb,
+            instance_buffer: None,
+            pipeline: None,
+        }
+    }
 }
 
 impl DrawablePrimitive for ImagePrimitive {
@@ -66,18 +80,15 @@ impl DrawablePrimitive for ImagePrimitive {
             depth_or_array_layers: 1,
         };
 
-        let texture = render_context
-            .device
-            .create_texture(&wgpu::TextureDescriptor {
-                label: Some("image primitive texture"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba8Unorm,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            });
+        let key = render_context.pool_key("texture");
+        let texture = render_context.gpu_pool.texture(
+            render_context.device,
+            key,
+            "image primitive texture",
+            texture_size,
+            TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        );
 
         render_context.queue.write_texture(
             wgpu::ImageCopyTexture {
@@ -109,32 +120,7 @@ impl DrawablePrimitive for ImagePrimitive {
                 ..Default::default()
             });
 
-        let texture_bind_group_layout =
-            render_context
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                multisampled: false,
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            // This should match the filterable field of the
-                            // corresponding Texture entry above.
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                    label: Some("texture_bind_group_layout"),
-                });
+        let texture_bind_group_layout = texture_bind_group_layout(render_context.device);
 
         let diffuse_bind_group =
             render_context
@@ -154,133 +140,142 @@ impl DrawablePrimitive for ImagePrimitive {
                     label: Some("diffuse_bind_group"),
                 });
 
-        ensure_pipeline(render_context, &texture_bind_group_layout);
+        self.pipeline = Some(render_context.pipeline::<ImagePipeline>());
+
+        UnitQuadBuffers::ensure(render_context);
 
-        self.ensure_buffers(render_context);
+        let key = render_context.pool_key("instance");
+        let instance_buffer = render_context.gpu_pool.buffer(
+            render_context.device,
+            key,
+            "image primitive instance buffer",
+            mem::size_of::<Quad>() as u64,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        );
+
+        let bb = self.bb.snap_to_pixels(render_context.scale_factor);
+
+        render_context.queue.write_buffer(
+            &instance_buffer,
+            0,
+            bytemuck::cast_slice(&[Quad {
+                pos: bb.pos.to_array(),
+                size: bb.size.to_array(),
+                color: [0.0, 0.0, 0.0, 0.0],
+            }]),
+        );
+
+        self.instance_buffer = Some(instance_buffer);
 
         render_context
             .bind_group_cache
-            .insert(PRIMITIVE_UUID, diffuse_bind_group);
+            .insert(BIND_GROUP_UUID, diffuse_bind_group);
+    }
+
+    fn pipeline_id(&self) -> Option<PipelineId> {
+        self.pipeline
     }
 
-    fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, ctx: &RenderContext<'a>) {
-        let pipeline = ctx.pipeline_cache.get(&PRIMITIVE_UUID).unwrap();
-        let bind_group = ctx.bind_group_cache.get(&PRIMITIVE_UUID).unwrap();
+    fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, ctx: &'a RenderContext<'a>) {
+        let bind_group = ctx.bind_group_cache.get(&BIND_GROUP_UUID).unwrap();
 
-        pass.set_pipeline(pipeline);
+        pass.set_pipeline(ctx.pipeline(self.pipeline.unwrap()));
         pass.set_bind_group(0, ctx.camera_bind_group, &[]);
         pass.set_bind_group(1, bind_group, &[]);
-        pass.set_vertex_buffer(0, self.vertex_buffer.as_ref().unwrap().slice(..));
+        UnitQuadBuffers::get(ctx.resources).bind(pass);
         pass.set_vertex_buffer(1, self.instance_buffer.as_ref().unwrap().slice(..));
-        pass.set_index_buffer(
-            self.index_buffer.as_ref().unwrap().slice(..),
-            wgpu::IndexFormat::Uint16,
-        );
         pass.draw_indexed(0..6, 0, 0..1);
     }
-}
-
-impl ImagePrimitive {
-    fn ensure_buffers(&mut self, render_context: &mut PrepareContext) {
-        if self.vertex_buffer.is_some() {
-            return;
-        }
 
-        let vertex_buffer =
-            render_context
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("quad index buffer"),
-                    contents: bytemuck::cast_slice(&QUAD_VERTICES),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
+    fn describe(&self, _resources: &mut QuirkyResources, display_list: &mut DisplayList) {
+        display_list.push(DisplayItem::Image {
+            pos: self.bb.pos,
+            size: self.bb.size,
+            image: Arc::new(self.data.clone()),
+            srgb: false,
+        });
+    }
+}
 
-        let index_buffer =
-            render_context
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: None,
-                    contents: bytemuck::cast_slice(&QUAD_INDEXES),
-                    usage: wgpu::BufferUsages::INDEX,
-                });
+fn texture_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                // This should match the filterable field of the
+                // corresponding Texture entry above.
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label: Some("texture_bind_group_layout"),
+    })
+}
 
-        let instance_buffer =
-            render_context
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("quad index buffer"),
-                    contents: bytemuck::cast_slice(&[Quad {
-                        pos: *self.bb.pos.as_vec2().as_ref(),
-                        size: *self.bb.size.as_vec2().as_ref(),
-                        color: [0.0, 0.0, 0.0, 0.0],
-                    }]),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
+/// The pipeline drawing [`ImagePrimitive`]
+pub struct ImagePipeline;
 
-        self.vertex_buffer = Some(vertex_buffer);
-        self.index_buffer = Some(index_buffer);
-        self.instance_buffer = Some(instance_buffer);
-    }
-}
+impl PipelineKind for ImagePipeline {
+    // Bind group layouts with identical entries are compatible, so the one of the pipeline can
+    // be created separately from the ones of the image bind groups
+    fn create(ctx: &PipelineContext) -> RenderPipeline {
+        let texture_bind_group_layout = texture_bind_group_layout(ctx.device);
 
-fn ensure_pipeline(
-    render_context: &mut PrepareContext,
-    texture_bind_group_layout: &BindGroupLayout,
-) {
-    if let std::collections::hash_map::Entry::Vacant(e) =
-        render_context.pipeline_cache.entry(PRIMITIVE_UUID)
-    {
         let render_pipeline_layout =
-            render_context
-                .device
+            ctx.device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                     label: Some("Render Pipeline Layout"),
                     bind_group_layouts: &[
-                        (render_context.camera_bind_group_layout),
-                        texture_bind_group_layout,
+                        (ctx.camera_bind_group_layout),
+                        &texture_bind_group_layout,
                     ], // NEW!
                     push_constant_ranges: &[],
                 });
 
-        let shader = render_context
-            .device
-            .create_shader_module(include_wgsl!("shaders/textured_quad.wgsl"));
+        let shader = shader_source!("src/primitives/shaders/textured_quad.wgsl").create_module(ctx);
 
-        let pipeline =
-            render_context
-                .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: None,
-                    layout: Some(&render_pipeline_layout),
-                    vertex: VertexState {
-                        module: &shader,
-                        entry_point: "vs_main",
-                        buffers: &[Vertex::LAYOUT, Quad::layout()],
-                    },
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        front_face: wgpu::FrontFace::Cw,
-                        cull_mode: Some(wgpu::Face::Back),
-                        ..Default::default()
-                    },
-                    depth_stencil: None,
-                    multisample: wgpu::MultisampleState {
-                        count: 1,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &shader,
-                        entry_point: "fs_main",
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: render_context.surface_format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                    }),
-                    multiview: None,
-                });
-
-        e.insert(pipeline);
+        ctx.device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&render_pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::LAYOUT, Quad::layout()],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: ctx.target.sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: ctx.target.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
     }
 }
@@ -4,3 +4,17 @@ pub mod image;
 pub mod quad;
 pub mod text;
 pub mod vertex;
+
+use glam::Vec2;
+use quirky::LayoutBox;
+
+/// Snaps the rect of an instance to physical pixels, see [`LayoutBox::snap_to_pixels`]
+pub(crate) fn snap_rect(pos: [f32; 2], size: [f32; 2], scale_factor: f64) -> ([f32; 2], [f32; 2]) {
+    let snapped = LayoutBox {
+        pos: Vec2::from(pos),
+        size: Vec2::from(size),
+    }
+    .snap_to_pixels(scale_factor);
+
+    (snapped.pos.to_array(), snapped.size.to_array())
+}
@@ -1,17 +1,18 @@
-use crate::primitives::vertex::{Vertex, QUAD_INDEXES, QUAD_VERTICES};
+use crate::primitives::snap_rect;
+use crate::primitives::vertex::{UnitQuadBuffers, Vertex};
 use futures_signals::signal::ReadOnlyMutable;
-use glam::UVec2;
+use glam::Vec2;
+use quirky::display_list::{DisplayItem, DisplayList};
 use quirky::drawable_primitive::DrawablePrimitive;
+use quirky::pipeline_registry::{PipelineContext, PipelineId, PipelineKind};
+use quirky::quirky_app_context::QuirkyResources;
 use quirky::render_contexts::{PrepareContext, RenderContext};
+use quirky::shader_source;
 use std::mem;
 use std::sync::Arc;
-use uuid::Uuid;
-use wgpu::util::DeviceExt;
-use wgpu::{include_wgsl, Device, PipelineLayoutDescriptor, RenderPipeline, VertexState};
+use wgpu::{PipelineLayoutDescriptor, RenderPipeline, VertexState};
 use wgpu_macros::VertexLayout;
 
-const QUAD_PIPELINE_ID: Uuid = Uuid::from_u128(0x94e8f8f8_e646_4e43_beef_c80b3688c998);
-
 #[repr(C)]
 #[derive(VertexLayout, bytemuck::Pod, bytemuck::Zeroable, Copy, Clone)]
 #[layout(Instance)]
@@ -22,11 +23,11 @@ pub struct Quad {
 }
 
 impl Quad {
-    pub fn new(pos: UVec2, size: UVec2, color: [f32; 4]) -> Self {
+    pub fn new(pos: Vec2, size: Vec2, color: [f32; 4]) -> Self {
         Self {
             color,
-            pos: pos.as_vec2().to_array(),
-            size: size.as_vec2().to_array(),
+            pos: pos.to_array(),
+            size: size.to_array(),
         }
     }
 
@@ -55,120 +56,156 @@ impl Quad {
     }
 }
 
+/// Instanced quads, merged with neighbouring quads into a single draw by the renderer.
 pub struct Quads {
     geometry: ReadOnlyMutable<Arc<[Quad]>>,
-    index_buffer: wgpu::Buffer,
-    instance_buffer: wgpu::Buffer,
-    vertex_buffer: wgpu::Buffer,
+    scale_factor: f64,
+    pipeline: Option<PipelineId>,
+    /// The instances as of the last prepare, for drawing the quads outside of a batch
+    instance_buffer: Option<(Arc<wgpu::Buffer>, u32)>,
 }
 
 impl Quads {
-    pub fn new(geometry: ReadOnlyMutable<Arc<[Quad]>>, device: &Device) -> Self {
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("quad index buffer"),
-            contents: bytemuck::cast_slice(&QUAD_VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&QUAD_INDEXES),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("quad index buffer"),
-            contents: bytemuck::cast_slice(geometry.lock_ref().as_ref()),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-
+    pub fn new(geometry: ReadOnlyMutable<Arc<[Quad]>>) -> Self {
         Self {
             geometry,
-            instance_buffer,
-            index_buffer,
-            vertex_buffer,
+            scale_factor: 1.0,
+            pipeline: None,
+            instance_buffer: None,
         }
     }
 }
 
 impl DrawablePrimitive for Quads {
     fn prepare(&mut self, prepare_context: &mut PrepareContext) {
-        if !prepare_context
-            .pipeline_cache
-            .contains_key(&QUAD_PIPELINE_ID)
-        {
-            prepare_context
-                .pipeline_cache
-                .insert(QUAD_PIPELINE_ID, configure_pipeline(prepare_context));
+        self.pipeline = Some(prepare_context.pipeline::<QuadPipeline>());
+
+        UnitQuadBuffers::ensure(prepare_context);
+
+        self.scale_factor = prepare_context.scale_factor;
+
+        let mut instances = vec![];
+        let instance_count = self.write_instances(&mut instances).unwrap_or_default();
+
+        let key = prepare_context.pool_key("instance");
+        let instance_buffer = prepare_context.gpu_pool.buffer(
+            prepare_context.device,
+            key,
+            "Quads instance buffer",
+            instances.len() as u64,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        );
+
+        prepare_context
+            .queue
+            .write_buffer(&instance_buffer, 0, &instances);
+
+        self.instance_buffer = Some((instance_buffer, instance_count));
+    }
+
+    fn pipeline_id(&self) -> Option<PipelineId> {
+        self.pipeline
+    }
+
+    fn write_instances(&self, out: &mut Vec<u8>) -> Option<u32> {
+        let geometry = self.geometry.lock_ref();
+
+        for quad in geometry.iter() {
+            let (pos, size) = snap_rect(quad.pos, quad.size, self.scale_factor);
+
+            out.extend_from_slice(bytemuck::bytes_of(&Quad { pos, size, ..*quad }));
         }
 
-        prepare_context.queue.write_buffer(
-            &self.instance_buffer,
-            0,
-            bytemuck::cast_slice(self.geometry.lock_ref().as_ref()),
-        )
+        Some(geometry.len() as u32)
     }
 
-    fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, render_context: &RenderContext<'a>) {
-        let pipeline = render_context
-            .pipeline_cache
-            .get(&QUAD_PIPELINE_ID)
-            .unwrap();
+    /// Draws the quads on their own, the renderer normally batches them through `draw_instanced`
+    fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, render_context: &'a RenderContext<'a>) {
+        if let Some((instance_buffer, instance_count)) = self.instance_buffer.as_ref() {
+            if *instance_count > 0 {
+                let size = *instance_count as u64 * mem::size_of::<Quad>() as u64;
+
+                self.draw_instanced(
+                    pass,
+                    render_context,
+                    instance_buffer.slice(..size),
+                    *instance_count,
+                );
+            }
+        }
+    }
 
-        pass.set_pipeline(pipeline);
+    fn draw_instanced<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        render_context: &'a RenderContext<'a>,
+        instances: wgpu::BufferSlice<'a>,
+        instance_count: u32,
+    ) {
+        pass.set_pipeline(render_context.pipeline(self.pipeline.unwrap()));
         pass.set_bind_group(0, render_context.camera_bind_group, &[]);
-        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        pass.draw_indexed(0..6, 0, 0..(self.geometry.lock_ref().len() as u32));
+        UnitQuadBuffers::get(render_context.resources).bind(pass);
+        pass.set_vertex_buffer(1, instances);
+        pass.draw_indexed(0..6, 0, 0..instance_count);
+    }
+
+    fn describe(&self, _resources: &mut QuirkyResources, display_list: &mut DisplayList) {
+        for quad in self.geometry.lock_ref().iter() {
+            display_list.push(DisplayItem::Quad {
+                pos: quad.pos.into(),
+                size: quad.size.into(),
+                color: quad.color,
+            });
+        }
     }
 }
 
-fn configure_pipeline(prepare_context: &PrepareContext) -> RenderPipeline {
-    let pipeline_layout =
-        prepare_context
+/// The pipeline drawing [`Quads`]
+pub struct QuadPipeline;
+
+impl PipelineKind for QuadPipeline {
+    fn create(ctx: &PipelineContext) -> RenderPipeline {
+        let pipeline_layout = ctx
             .device
             .create_pipeline_layout(&PipelineLayoutDescriptor {
                 label: None,
-                bind_group_layouts: &[prepare_context.camera_bind_group_layout],
+                bind_group_layouts: &[ctx.camera_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-    let shader = prepare_context
-        .device
-        .create_shader_module(include_wgsl!("shaders/quad.wgsl"));
-
-    prepare_context
-        .device
-        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::LAYOUT, Quad::layout()],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: Some(wgpu::Face::Back),
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: prepare_context.surface_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        })
+        let shader = shader_source!("src/primitives/shaders/quad.wgsl").create_module(ctx);
+
+        ctx.device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::LAYOUT, Quad::layout()],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: ctx.target.sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: ctx.target.format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+    }
 }
@@ -1,15 +1,188 @@
 use crate::resources::font_resource::FontResource;
-use glyphon::TextRenderer;
+use glam::{ivec2, uvec2, Affine2};
+use glyphon::{Buffer, Color, Resolution, TextArea, TextAtlas, TextBounds, TextRenderer};
+use quirky::display_list::{DisplayItem, DisplayList, PixelSpan};
 use quirky::drawable_primitive::DrawablePrimitive;
-use quirky::render_contexts::RenderContext;
+use quirky::quirky_app_context::QuirkyResources;
+use quirky::render_contexts::{PrepareContext, RenderContext};
+use quirky::LayoutBox;
+use std::any::TypeId;
+use std::sync::{Arc, RwLock};
 use wgpu::RenderPass;
 
-pub struct TextRendererPrimitive(pub TextRenderer);
+/// Draws a shaped text buffer, clipped to the bounding box it is positioned at.
+///
+/// The buffer is shaped in logical units, and scaled to the app's scale factor when drawn.
+/// Widget transforms move and scale the text. Glyphon cannot rotate, skew or unevenly scale
+/// text, so such transforms are warned about, and the text is drawn upright at their average
+/// scale.
+pub struct TextRendererPrimitive {
+    buffer: Arc<RwLock<Option<Buffer>>>,
+    bb: LayoutBox,
+    color: Color,
+    renderer: Option<TextRenderer>,
+}
+
+impl TextRendererPrimitive {
+    pub fn new(buffer: Arc<RwLock<Option<Buffer>>>, bb: LayoutBox, color: Color) -> Self {
+        Self {
+            buffer,
+            bb,
+            color,
+            renderer: None,
+        }
+    }
+}
+
+/// The scale of a transform that only moves and uniformly scales, the only ones text can be
+/// drawn with
+fn uniform_scale(transform: &Affine2) -> Option<f32> {
+    let (x_axis, y_axis) = (transform.matrix2.x_axis, transform.matrix2.y_axis);
+    let scale = x_axis.x;
+    let tolerance = 1e-4 * scale.abs().max(1.0);
+
+    let uniform = scale > 0.0
+        && x_axis.y.abs() <= tolerance
+        && y_axis.x.abs() <= tolerance
+        && (y_axis.y - scale).abs() <= tolerance;
+
+    uniform.then_some(scale)
+}
 
 impl DrawablePrimitive for TextRendererPrimitive {
+    fn prepare(&mut self, prepare_context: &mut PrepareContext) {
+        let font_resource = prepare_context
+            .resources
+            .get_resource_mut::<FontResource>(TypeId::of::<FontResource>())
+            .unwrap();
+
+        let text_atlas = font_resource.text_atlas.get_or_insert_with(|| {
+            TextAtlas::new(
+                prepare_context.device,
+                prepare_context.queue,
+                prepare_context.surface_format,
+            )
+        });
+
+        let multisample = wgpu::MultisampleState {
+            count: prepare_context.sample_count,
+            ..Default::default()
+        };
+
+        let renderer = self.renderer.get_or_insert_with(|| {
+            TextRenderer::new(text_atlas, prepare_context.device, multisample, None)
+        });
+
+        let buffer_lock = self.buffer.read().unwrap();
+
+        let Some(buffer) = buffer_lock.as_ref() else {
+            return;
+        };
+
+        // Glyphon works in physical pixels, scaling the logically sized text as it rasterizes it
+        let transform = prepare_context.transform;
+        let scale_factor = prepare_context.scale_factor;
+        let text_scale = scale_factor as f32
+            * uniform_scale(&transform).unwrap_or_else(|| {
+                tracing::warn!(
+                    ?transform,
+                    "text cannot be rotated, skewed or unevenly scaled, drawing it upright"
+                );
+
+                transform.matrix2.determinant().abs().sqrt()
+            });
+        let bb = self.bb.transformed(&transform).to_physical(scale_factor);
+        let screen_resolution = prepare_context.screen_resolution;
+
+        let _ = renderer.prepare(
+            prepare_context.device,
+            prepare_context.queue,
+            &mut font_resource.font_system,
+            text_atlas,
+            Resolution {
+                width: screen_resolution.x,
+                height: screen_resolution.y,
+            },
+            [TextArea {
+                buffer,
+                left: bb.pos.x,
+                top: bb.pos.y,
+                scale: text_scale,
+                bounds: TextBounds {
+                    left: bb.pos.x as i32,
+                    top: bb.pos.y as i32,
+                    right: (bb.pos.x as i32 + bb.size.x as i32).min(screen_resolution.x as i32),
+                    bottom: (bb.pos.y as i32 + bb.size.y as i32).min(screen_resolution.y as i32),
+                },
+                default_color: self.color,
+            }],
+            &mut font_resource.font_cache,
+        );
+    }
+
     fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, render_context: &'a RenderContext<'a>) {
         let font_resource: &FontResource = render_context.resources.get_resource().unwrap();
 
-        let _ = self.0.render(&font_resource.text_atlas, pass);
+        if let (Some(renderer), Some(text_atlas)) =
+            (self.renderer.as_ref(), font_resource.text_atlas.as_ref())
+        {
+            let _ = renderer.render(text_atlas, pass);
+        }
+    }
+
+    fn describe(&self, resources: &mut QuirkyResources, display_list: &mut DisplayList) {
+        let font_resource = resources
+            .get_resource_mut::<FontResource>(TypeId::of::<FontResource>())
+            .unwrap();
+
+        let buffer_lock = self.buffer.read().unwrap();
+
+        let Some(buffer) = buffer_lock.as_ref() else {
+            return;
+        };
+
+        let origin = self.bb.pos.as_ivec2();
+        let mut spans = vec![];
+
+        buffer.draw(
+            &mut font_resource.font_system,
+            &mut font_resource.font_cache,
+            self.color,
+            |x, y, w, h, color| {
+                spans.push(PixelSpan {
+                    pos: origin + ivec2(x, y),
+                    size: uvec2(w, h),
+                    color: [color.r(), color.g(), color.b(), color.a()],
+                })
+            },
+        );
+
+        display_list.push(DisplayItem::Spans {
+            clip: self.bb,
+            spans,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::primitives::text::uniform_scale;
+    use glam::{vec2, Affine2};
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn text_is_only_moved_and_uniformly_scaled() {
+        assert_eq!(uniform_scale(&Affine2::IDENTITY), Some(1.0));
+        assert_eq!(
+            uniform_scale(&Affine2::from_scale_angle_translation(
+                vec2(2.0, 2.0),
+                0.0,
+                vec2(10.0, 5.0),
+            )),
+            Some(2.0)
+        );
+        assert_eq!(uniform_scale(&Affine2::from_angle(FRAC_PI_2)), None);
+        assert_eq!(uniform_scale(&Affine2::from_scale(vec2(2.0, 1.0))), None);
+        assert_eq!(uniform_scale(&Affine2::from_scale(vec2(-1.0, -1.0))), None);
     }
 }
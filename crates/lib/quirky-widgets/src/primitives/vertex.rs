@@ -1,3 +1,6 @@
+use quirky::quirky_app_context::QuirkyResources;
+use quirky::render_contexts::PrepareContext;
+use wgpu::util::DeviceExt;
 use wgpu_macros::VertexLayout;
 
 #[derive(VertexLayout, bytemuck::Pod, bytemuck::Zeroable, Copy, Clone)]
@@ -27,3 +30,47 @@ pub const QUAD_VERTICES: [Vertex; 4] = [
 ];
 
 pub const QUAD_INDEXES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+/// The vertices and indexes of [`QUAD_VERTICES`], shared by all primitives drawing instanced quads
+pub(crate) struct UnitQuadBuffers {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+}
+
+impl UnitQuadBuffers {
+    pub fn ensure(prepare_context: &mut PrepareContext) {
+        if prepare_context.resources.contains::<UnitQuadBuffers>() {
+            return;
+        }
+
+        let buffers = UnitQuadBuffers {
+            vertex_buffer: prepare_context.device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("quad vertex buffer"),
+                    contents: bytemuck::cast_slice(&QUAD_VERTICES),
+                    usage: wgpu::BufferUsages::VERTEX,
+                },
+            ),
+            index_buffer: prepare_context.device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("quad index buffer"),
+                    contents: bytemuck::cast_slice(&QUAD_INDEXES),
+                    usage: wgpu::BufferUsages::INDEX,
+                },
+            ),
+        };
+
+        prepare_context.resources.insert(buffers);
+    }
+
+    pub fn get(resources: &QuirkyResources) -> &Self {
+        resources
+            .get_resource::<UnitQuadBuffers>()
+            .expect("unit quad buffers are created when preparing")
+    }
+
+    pub fn bind<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+    }
+}
@@ -1,7 +1,43 @@
+use glyphon::cosmic_text::fontdb;
 use glyphon::{FontSystem, SwashCache, TextAtlas};
+use std::sync::Mutex;
 
 pub struct FontResource {
     pub font_system: FontSystem,
     pub font_cache: SwashCache,
-    pub text_atlas: TextAtlas,
+    /// Created by the first text primitive prepared on a device
+    pub text_atlas: Option<TextAtlas>,
+}
+
+/// Font systems for shaping text on several threads at once, all sharing the fonts of the
+/// [`FontResource::font_system`] the pool was created from.
+///
+/// Fonts loaded into that font system afterwards are not picked up by the pool.
+pub struct FontSystemPool {
+    locale: String,
+    db: fontdb::Database,
+    font_systems: Mutex<Vec<FontSystem>>,
+}
+
+impl FontSystemPool {
+    pub fn new(font_system: &FontSystem) -> Self {
+        Self {
+            locale: font_system.locale().to_string(),
+            db: font_system.db().clone(),
+            font_systems: Default::default(),
+        }
+    }
+
+    /// Runs `f` with a font system no other thread is using, creating one if they all are
+    pub fn with_font_system<R>(&self, f: impl FnOnce(&mut FontSystem) -> R) -> R {
+        let pooled = self.font_systems.lock().unwrap().pop();
+        let mut font_system = pooled.unwrap_or_else(|| {
+            FontSystem::new_with_locale_and_db(self.locale.clone(), self.db.clone())
+        });
+
+        let result = f(&mut font_system);
+        self.font_systems.lock().unwrap().push(font_system);
+
+        result
+    }
 }
@@ -1,7 +1,8 @@
-#[derive(Clone, Copy, Default, Eq, PartialEq)]
+/// Space around the content of a container, in logical units
+#[derive(Clone, Copy, Default, PartialEq)]
 pub struct Padding {
-    pub left: u32,
-    pub right: u32,
-    pub top: u32,
-    pub bottom: u32,
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
 }
@@ -5,15 +5,15 @@ use crate::theming::QuirkyTheme;
 use async_trait::async_trait;
 use futures::{FutureExt, StreamExt};
 use futures_signals::signal::{always, Mutable, Signal, SignalExt};
-use glam::UVec2;
+use glam::Vec2;
 use quirky::clone;
 use quirky::drawable_primitive::DrawablePrimitive;
-use quirky::quirky_app_context::QuirkyAppContext;
-use quirky::render_contexts::PrepareContext;
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
 use quirky::widget::{SizeConstraint, Widget, WidgetBase};
 use quirky::widgets::event_subscribe::run_subscribe_to_events;
 use quirky::widgets::events::{MouseButton, MouseEvent, WidgetEvent};
 use quirky::widgets::layout_helper::layout;
+use quirky::LayoutBox;
 use quirky_macros::widget;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -36,7 +36,7 @@ pub struct Button {
     #[signal_prop]
     content: Arc<dyn Widget>,
     #[signal_prop]
-    #[default(SizeConstraint::MaxHeight(32))]
+    #[default(SizeConstraint::MaxHeight(32.0))]
     size_constraint: SizeConstraint,
     #[slot]
     on_click: ClickEvent,
@@ -75,11 +75,10 @@ impl<
 
     fn prepare(
         &self,
-        quirky_context: &QuirkyAppContext,
-        _paint_ctx: &mut PrepareContext,
+        _quirky_context: &QuirkyAppContext,
+        _resources: &mut QuirkyResources,
     ) -> Vec<Box<dyn DrawablePrimitive>> {
-        let button_primitive =
-            ButtonPrimitive::new(self.button_data.read_only(), &quirky_context.device);
+        let button_primitive = ButtonPrimitive::new(self.button_data.read_only());
 
         vec![Box::new(button_primitive)]
     }
@@ -88,7 +87,11 @@ impl<
         Box::new((self.size_constraint)())
     }
 
-    fn get_widget_at(&self, pos: UVec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    fn clip_rect(&self) -> Option<LayoutBox> {
+        Some(self.bounding_box.get())
+    }
+
+    fn get_widget_at(&self, pos: Vec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
         let bb = self.bounding_box().get();
 
         if bb.contains(pos) {
@@ -121,6 +124,7 @@ impl<
         };
 
         let button_data = self.button_data.clone();
+        let widget = self.clone();
 
         let state_change_fut = self.button_state.signal().for_each(move |data| {
             let color = color_func(data);
@@ -128,15 +132,17 @@ impl<
             let mut data = button_data.get();
             data.color = color;
             button_data.set(data);
+            widget.set_dirty();
 
             async move { ctx.signal_redraw().await }
         });
 
         let bb_change_fut = self.bounding_box.signal().for_each(|new_bb| {
             let mut data = self.button_data.get();
-            data.pos = *new_bb.pos.as_vec2().as_ref();
-            data.size = *new_bb.size.as_vec2().as_ref();
+            data.pos = new_bb.pos.to_array();
+            data.size = new_bb.size.to_array();
             self.button_data.set(data);
+            self.set_dirty();
 
             async move { ctx.signal_redraw().await }
         });
@@ -178,10 +184,10 @@ impl<
                 .to_signal_vec(),
             always((
                 Padding {
-                    left: 3,
-                    right: 3,
-                    top: 3,
-                    bottom: 3,
+                    left: 3.0,
+                    right: 3.0,
+                    top: 3.0,
+                    bottom: 3.0,
                 },
                 AnchorPoint::Center,
             )),
@@ -217,3 +223,68 @@ impl<
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::theming::QuirkyTheme;
+    use crate::widgets::button::ButtonBuilder;
+    use crate::widgets::slab::transparent_slab;
+    use futures_signals::signal::Mutable;
+    use glam::{uvec2, vec2, UVec2};
+    use quirky::input_driver::{InputDriver, WidgetTreeTarget};
+    use quirky::quirky_app_context::QuirkyAppContext;
+    use quirky::widget::{Widget, WidgetBase};
+    use quirky::widgets::events::MouseButton;
+    use quirky::{clone, LayoutBox};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn clicking_a_button_fires_on_click() {
+        let (tx, _rx) = async_std::channel::unbounded();
+        let ctx = QuirkyAppContext::new(
+            Mutable::new(UVec2::new(200, 100)).read_only(),
+            tx,
+            Default::default(),
+        );
+
+        ctx.resources
+            .lock()
+            .unwrap()
+            .insert(Mutable::new(QuirkyTheme::dark_default()));
+
+        let clicks = Arc::new(AtomicUsize::new(0));
+
+        let button = ButtonBuilder::new()
+            .content(transparent_slab())
+            .on_click(clone!(clicks, move |_| {
+                clicks.fetch_add(1, Ordering::SeqCst);
+            }))
+            .build();
+
+        button.set_bounding_box(LayoutBox {
+            pos: vec2(10.0, 10.0),
+            size: vec2(100.0, 30.0),
+        });
+
+        let interact = async {
+            // Give the button a chance to subscribe to its events
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            let mut input = InputDriver::new(WidgetTreeTarget::new(button.clone(), &ctx));
+
+            input.click(uvec2(150, 50), MouseButton::Left);
+            input.click(uvec2(20, 20), MouseButton::Left);
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+
+        tokio::select! {
+            _ = button.clone().run(&ctx) => unreachable!("button run loop finished"),
+            _ = interact => {}
+        }
+
+        assert_eq!(clicks.load(Ordering::SeqCst), 1);
+    }
+}
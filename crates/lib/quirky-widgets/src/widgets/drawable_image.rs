@@ -2,12 +2,11 @@ use crate::primitives::image::ImagePrimitive;
 use async_trait::async_trait;
 use futures::{FutureExt, StreamExt};
 use futures_signals::signal::{Mutable, SignalExt};
-use glam::UVec2;
+use glam::{UVec2, Vec2};
 use image::{Rgba, RgbaImage};
 use quirky::clone;
 use quirky::drawable_primitive::DrawablePrimitive;
-use quirky::quirky_app_context::QuirkyAppContext;
-use quirky::render_contexts::PrepareContext;
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
 use quirky::widget::{Widget, WidgetBase};
 use quirky::widgets::events::{MouseButton, MouseEvent, WidgetEvent};
 use quirky_macros::widget;
@@ -25,24 +24,16 @@ impl Widget for DrawableImage {
     fn prepare(
         &self,
         _ctx: &QuirkyAppContext,
-        _paint_ctx: &mut PrepareContext,
+        _resources: &mut QuirkyResources,
     ) -> Vec<Box<dyn DrawablePrimitive>> {
         let bb = self.bounding_box.get();
 
         let image = self.image.get_cloned();
 
-        let tex = ImagePrimitive {
-            data: image,
-            vertex_buffer: None,
-            index_buffer: None,
-            instance_buffer: None,
-            bb,
-        };
-
-        vec![Box::new(tex)]
+        vec![Box::new(ImagePrimitive::new(image, bb))]
     }
 
-    fn get_widget_at(&self, pos: UVec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    fn get_widget_at(&self, pos: Vec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
         let bb = self.bounding_box.get();
 
         if bb.contains(pos) {
@@ -75,12 +66,9 @@ impl Widget for DrawableImage {
                                     let mut image = self.image.lock_mut();
                                     let bb = self.bounding_box.get();
 
-                                    let to = mouse_pos.get();
-                                    let put_x = to.x as i32 - bb.pos.x as i32;
-                                    let put_y = to.y as i32 - bb.pos.y as i32;
-
-                                    let px = (put_x as f32 / bb.size.x as f32) * 1024.0;
-                                    let py = (put_y as f32 / bb.size.y as f32) * 1024.0;
+                                    let to = mouse_pos.get().as_vec2();
+                                    let put = (to - bb.pos) / bb.size * 1024.0;
+                                    let (px, py) = (put.x, put.y);
 
                                     let color = match button {
                                         MouseButton::Left => Rgba([255, 0, 0, 255]),
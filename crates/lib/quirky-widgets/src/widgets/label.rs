@@ -1,21 +1,18 @@
 use crate::primitives::text::TextRendererPrimitive;
-use crate::resources::font_resource::FontResource;
+use crate::resources::font_resource::FontSystemPool;
 use async_trait::async_trait;
 use futures::{FutureExt, StreamExt};
 use futures_signals::map_ref;
 use futures_signals::signal::{Signal, SignalExt};
 use glyphon::cosmic_text::Align;
 use glyphon::{
-    Attrs, Buffer, BufferLine, Color, FamilyOwned, Metrics, Resolution, Shaping, Stretch, Style,
-    TextArea, TextBounds, TextRenderer, Weight,
+    Attrs, Buffer, BufferLine, Color, FamilyOwned, Metrics, Shaping, Stretch, Style, Weight,
 };
 use quirky::drawable_primitive::DrawablePrimitive;
-use quirky::quirky_app_context::QuirkyAppContext;
-use quirky::render_contexts::PrepareContext;
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources, SharedResources};
 use quirky::widget::SizeConstraint;
 use quirky::widget::{Widget, WidgetBase};
 use quirky_macros::widget;
-use std::borrow::BorrowMut;
 use std::sync::{Arc, RwLock};
 
 #[derive(Clone)]
@@ -82,113 +79,78 @@ impl<
         TextColorSignalFn,
     >
 {
-    fn prepare(
-        &self,
-        quirky_context: &QuirkyAppContext,
-        prepare_context: &mut PrepareContext,
-    ) -> Vec<Box<dyn DrawablePrimitive>> {
-        let font_resource = prepare_context
-            .resources
-            .get_resource_mut::<FontResource>(std::any::TypeId::of::<FontResource>())
-            .unwrap();
+    fn prepare_parallel(&self, _quirky_context: &QuirkyAppContext, resources: &SharedResources) {
+        let font_systems = resources.get_resource::<FontSystemPool>().unwrap();
 
         let bb = self.bounding_box.get();
         let mut buffer_lock = self.text_buffer.write().unwrap();
 
         let font_settings = self.font_settings_prop_value.get_cloned().unwrap();
 
-        let buffer = if let Some(mut buf) = buffer_lock.take() {
-            buf.set_size(
-                &mut font_resource.font_system,
-                bb.size.x as f32,
-                bb.size.y as f32,
-            );
-
-            buf.set_text(
-                &mut font_resource.font_system,
-                &self.text_prop_value.get_cloned().unwrap_or("".into()),
-                Attrs::new().family(font_settings.family.as_family()),
-                Shaping::Advanced,
-            );
-            buf.lines.iter_mut().for_each(|line: &mut BufferLine| {
-                line.set_align(self.text_align_prop_value.get());
-            });
-
-            buf.shape_until_scroll(&mut font_resource.font_system);
-
-            buf
-        } else {
-            let mut buffer = Buffer::new(&mut font_resource.font_system, font_settings.metrics);
-
-            buffer.set_size(
-                &mut font_resource.font_system,
-                bb.size.x as f32,
-                bb.size.y as f32,
-            );
-
-            buffer.set_text(
-                &mut font_resource.font_system,
-                &self
-                    .text_prop_value
-                    .get_cloned()
-                    .or(Some("".into()))
-                    .unwrap(),
-                Attrs::new().family(font_settings.family.as_family()),
-                Shaping::Advanced,
-            );
-
-            buffer.lines.iter_mut().for_each(|line: &mut BufferLine| {
-                line.set_align(self.text_align_prop_value.get());
-            });
-
-            buffer.shape_until_scroll(&mut font_resource.font_system);
-
-            buffer
-        };
-
-        let mut renderer = TextRenderer::new(
-            &mut font_resource.text_atlas.borrow_mut(),
-            &quirky_context.device,
-            Default::default(),
-            None,
-        );
-
-        let screen_resolution = quirky_context.viewport_size.get();
-        let buffer = buffer_lock.insert(buffer);
+        let buffer = font_systems.with_font_system(|font_system| {
+            if let Some(mut buf) = buffer_lock.take() {
+                buf.set_size(font_system, bb.size.x, bb.size.y);
+
+                buf.set_text(
+                    font_system,
+                    &self.text_prop_value.get_cloned().unwrap_or("".into()),
+                    Attrs::new().family(font_settings.family.as_family()),
+                    Shaping::Advanced,
+                );
+                buf.lines.iter_mut().for_each(|line: &mut BufferLine| {
+                    line.set_align(self.text_align_prop_value.get());
+                });
+
+                buf.shape_until_scroll(font_system);
+
+                buf
+            } else {
+                let mut buffer = Buffer::new(font_system, font_settings.metrics);
+
+                buffer.set_size(font_system, bb.size.x, bb.size.y);
+
+                buffer.set_text(
+                    font_system,
+                    &self
+                        .text_prop_value
+                        .get_cloned()
+                        .or(Some("".into()))
+                        .unwrap(),
+                    Attrs::new().family(font_settings.family.as_family()),
+                    Shaping::Advanced,
+                );
+
+                buffer.lines.iter_mut().for_each(|line: &mut BufferLine| {
+                    line.set_align(self.text_align_prop_value.get());
+                });
+
+                buffer.shape_until_scroll(font_system);
+
+                buffer
+            }
+        });
+
+        *buffer_lock = Some(buffer);
+    }
 
+    fn prepare(
+        &self,
+        _quirky_context: &QuirkyAppContext,
+        _resources: &mut QuirkyResources,
+    ) -> Vec<Box<dyn DrawablePrimitive>> {
+        let bb = self.bounding_box.get();
         let text_color = self.text_color_prop_value.get().unwrap();
 
-        let _ = renderer.prepare(
-            &quirky_context.device,
-            &quirky_context.queue,
-            &mut font_resource.font_system.borrow_mut(),
-            &mut font_resource.text_atlas.borrow_mut(),
-            Resolution {
-                width: screen_resolution.x,
-                height: screen_resolution.y,
-            },
-            [TextArea {
-                buffer,
-                left: bb.pos.x as f32,
-                top: bb.pos.y as f32,
-                scale: 1.0,
-                bounds: TextBounds {
-                    left: bb.pos.x as i32,
-                    top: bb.pos.y as i32,
-                    right: (bb.pos.x as i32 + bb.size.x as i32).min(screen_resolution.x as i32),
-                    bottom: (bb.pos.y as i32 + bb.size.y as i32).min(screen_resolution.y as i32),
-                },
-                default_color: Color::rgba(
-                    (text_color[0] * 256.0) as u8,
-                    (text_color[1] * 256.0) as u8,
-                    (text_color[2] * 256.0) as u8,
-                    (text_color[3] * 256.0) as u8,
-                ),
-            }],
-            &mut font_resource.font_cache.borrow_mut(),
-        );
-
-        vec![Box::new(TextRendererPrimitive { 0: renderer })]
+        vec![Box::new(TextRendererPrimitive::new(
+            self.text_buffer.clone(),
+            bb,
+            Color::rgba(
+                (text_color[0] * 256.0) as u8,
+                (text_color[1] * 256.0) as u8,
+                (text_color[2] * 256.0) as u8,
+                (text_color[3] * 256.0) as u8,
+            ),
+        ))]
     }
 
     fn size_constraint(&self) -> Box<dyn Signal<Item = SizeConstraint> + Unpin + Send> {
@@ -199,7 +161,7 @@ impl<
 
             let metrics = font_settings.as_ref().unwrap().metrics;
 
-            SizeConstraint::MaxHeight(metrics.line_height as u32)
+            SizeConstraint::MaxHeight(metrics.line_height)
         }))
     }
 
@@ -1,6 +1,7 @@
 pub mod button;
 pub mod drawable_image;
 pub mod label;
+pub mod opacity;
 pub mod slab;
 pub mod stack;
 pub mod text_input;
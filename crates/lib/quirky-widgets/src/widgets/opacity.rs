@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use futures::{FutureExt, StreamExt};
+use futures_signals::signal::{always, Signal, SignalExt};
+use quirky::opacity_groups::{BlendMode, OpacityGroup};
+use quirky::quirky_app_context::QuirkyAppContext;
+use quirky::widget::{SizeConstraint, Widget, WidgetBase};
+use quirky_macros::widget;
+use quirky_utils::futures_map_poll::FuturesMapPoll;
+use std::sync::Arc;
+
+/// Draws its child into an intermediate texture, composited with a group opacity and blend mode.
+///
+/// Fading the whole subtree this way leaves overlapping primitives within it opaque relative to
+/// each other. The child shares the bounding box of the container, and is clipped to it.
+#[widget]
+pub struct Opacity {
+    #[signal_prop]
+    child: Arc<dyn Widget>,
+    #[signal_prop]
+    #[default(1.0)]
+    #[force_repaint]
+    opacity: f32,
+    #[signal_prop]
+    #[default(BlendMode::Normal)]
+    #[force_repaint]
+    blend_mode: BlendMode,
+}
+
+#[async_trait]
+impl<
+        ChildSignal: futures_signals::signal::Signal<Item = Arc<dyn Widget>> + Send + Sync + Unpin + 'static,
+        ChildSignalFn: Fn() -> ChildSignal + Send + Sync + 'static,
+        OpacitySignal: futures_signals::signal::Signal<Item = f32> + Send + Sync + Unpin + 'static,
+        OpacitySignalFn: Fn() -> OpacitySignal + Send + Sync + 'static,
+        BlendModeSignal: futures_signals::signal::Signal<Item = BlendMode> + Send + Sync + Unpin + 'static,
+        BlendModeSignalFn: Fn() -> BlendModeSignal + Send + Sync + 'static,
+    > Widget
+    for Opacity<
+        ChildSignal,
+        ChildSignalFn,
+        OpacitySignal,
+        OpacitySignalFn,
+        BlendModeSignal,
+        BlendModeSignalFn,
+    >
+{
+    fn children(&self) -> Option<Vec<Arc<dyn Widget>>> {
+        self.child_prop_value.get_cloned().map(|v| vec![v])
+    }
+
+    fn size_constraint(&self) -> Box<dyn Signal<Item = SizeConstraint> + Unpin + Send> {
+        Box::new(
+            self.child_prop_value
+                .signal_cloned()
+                .map(|child| match child {
+                    Some(child) => child.size_constraint().boxed(),
+                    None => always(SizeConstraint::Unconstrained).boxed(),
+                })
+                .flatten(),
+        )
+    }
+
+    fn opacity_group(&self) -> Option<OpacityGroup> {
+        Some(OpacityGroup {
+            opacity: self.opacity_prop_value.get().unwrap_or(1.0),
+            blend_mode: self.blend_mode_prop_value.get().unwrap_or_default(),
+        })
+    }
+
+    async fn run(self: Arc<Self>, ctx: &QuirkyAppContext) {
+        let mut futs = self.poll_prop_futures(ctx);
+
+        let bb_update = self.bounding_box.signal().for_each(|new_bb| {
+            if let Some(child) = self.child_prop_value.lock_ref().as_ref() {
+                child.set_bounding_box(new_bb);
+            }
+
+            async move {
+                ctx.signal_redraw().await;
+            }
+        });
+
+        let (pollable, data) = FuturesMapPoll::new();
+        let bounding_box = self.bounding_box.clone();
+
+        let child_runner = self
+            .child_prop_value
+            .signal_cloned()
+            .for_each(move |child| {
+                if let Some(child) = child {
+                    child.set_bounding_box(bounding_box.get());
+                    data.clear();
+                    data.insert(&child.id(), child.run(ctx));
+                }
+                async move {}
+            });
+
+        futs.push(pollable.boxed());
+        futs.push(child_runner.boxed());
+        futs.push(bb_update.boxed());
+
+        loop {
+            let _ = futs.select_next_some().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::widgets::opacity::OpacityBuilder;
+    use crate::widgets::slab::transparent_slab;
+    use quirky::opacity_groups::{BlendMode, OpacityGroup};
+    use quirky::widget::Widget;
+
+    #[test]
+    fn opacity_defaults_to_an_opaque_group() {
+        let opacity = OpacityBuilder::new().child(transparent_slab()).build();
+
+        assert_eq!(
+            opacity.opacity_group(),
+            Some(OpacityGroup {
+                opacity: 1.0,
+                blend_mode: BlendMode::Normal,
+            })
+        );
+    }
+}
@@ -4,10 +4,9 @@ use futures::{FutureExt, StreamExt};
 use futures_signals::map_ref;
 use futures_signals::signal::Signal;
 use futures_signals::signal::{Mutable, SignalExt};
-use glam::UVec2;
+use glam::Vec2;
 use quirky::drawable_primitive::DrawablePrimitive;
-use quirky::quirky_app_context::QuirkyAppContext;
-use quirky::render_contexts::PrepareContext;
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
 use quirky::widget::SizeConstraint;
 use quirky::widget::{Event, Widget, WidgetBase};
 use quirky::widgets::event_subscribe::run_subscribe_to_events;
@@ -44,8 +43,8 @@ impl<
         let bb = self.bounding_box.get();
         let mut size = bb.size;
 
-        if bb.size.length_squared() == 0 {
-            size = UVec2::new(1, 1);
+        if bb.size.length_squared() == 0.0 {
+            size = Vec2::ONE;
         }
 
         let color = if self.is_hovered.get() {
@@ -77,11 +76,11 @@ impl<
 {
     fn prepare(
         &self,
-        ctx: &QuirkyAppContext,
-        _paint_ctx: &mut PrepareContext,
+        _ctx: &QuirkyAppContext,
+        _resources: &mut QuirkyResources,
     ) -> Vec<Box<dyn DrawablePrimitive>> {
         self.regenerate_primitives();
-        let quads = Box::new(Quads::new(self.quad_geometry.read_only(), &ctx.device));
+        let quads = Box::new(Quads::new(self.quad_geometry.read_only()));
 
         vec![quads]
     }
@@ -90,7 +89,7 @@ impl<
         Box::new((self.size_constraint)())
     }
 
-    fn get_widget_at(&self, pos: UVec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    fn get_widget_at(&self, pos: Vec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
         let bb = self.bounding_box.get();
 
         if bb.contains(pos) {
@@ -111,6 +110,7 @@ impl<
         }
         .for_each(|_| {
             self.regenerate_primitives();
+            self.set_dirty();
             async move {
                 ctx.signal_redraw().await;
             }
@@ -145,6 +145,14 @@ impl<
     }
 }
 
+/// A transparent slab for tests that need a widget to fill a tree
+#[cfg(test)]
+pub(crate) fn transparent_slab() -> Arc<dyn Widget> {
+    SlabBuilder::new()
+        .color_signal(|| futures_signals::signal::always([0.0, 0.0, 0.0, 0.0]))
+        .build()
+}
+
 #[cfg(test)]
 mod test {
     use crate::widgets::slab::SlabBuilder;
@@ -6,6 +6,7 @@ use quirky::quirky_app_context::QuirkyAppContext;
 use quirky::widget::SizeConstraint;
 use quirky::widget::{Widget, WidgetBase};
 use quirky::widgets::run_widget::run_widgets;
+use quirky::LayoutBox;
 use quirky_macros::widget;
 use std::sync::Arc;
 
@@ -19,12 +20,19 @@ pub struct Stack {
     #[signal_prop]
     #[default(Default::default())]
     size_constraint: SizeConstraint,
+
+    /// Clips the children to the bounding box, rather than letting them draw outside of it
+    #[signal_prop]
+    #[default(false)]
+    clip: bool,
 }
 
 #[async_trait]
 impl<
         SizeConstraintSignal: futures_signals::signal::Signal<Item = SizeConstraint> + Send + Sync + Unpin + 'static,
         SizeConstraintSignalFn: Fn() -> SizeConstraintSignal + Send + Sync + 'static,
+        ClipSignal: futures_signals::signal::Signal<Item = bool> + Send + Sync + Unpin + 'static,
+        ClipSignalFn: Fn() -> ClipSignal + Send + Sync + 'static,
         ChildrenSignal: futures_signals::signal_vec::SignalVec<Item = Arc<dyn Widget>>
             + Send
             + Sync
@@ -32,7 +40,14 @@ impl<
             + 'static,
         ChildrenSignalFn: Fn() -> ChildrenSignal + Send + Sync + 'static,
     > Widget
-    for Stack<SizeConstraintSignal, SizeConstraintSignalFn, ChildrenSignal, ChildrenSignalFn>
+    for Stack<
+        SizeConstraintSignal,
+        SizeConstraintSignalFn,
+        ClipSignal,
+        ClipSignalFn,
+        ChildrenSignal,
+        ChildrenSignalFn,
+    >
 {
     fn children(&self) -> Option<Vec<Arc<dyn Widget>>> {
         let children = self.children_prop_value.lock_ref().to_vec();
@@ -48,6 +63,10 @@ impl<
         )
     }
 
+    fn clip_rect(&self) -> Option<LayoutBox> {
+        (self.clip_prop_value.get() == Some(true)).then(|| self.bounding_box.get())
+    }
+
     async fn run(self: Arc<Self>, ctx: &QuirkyAppContext) {
         let mut futs = self.poll_prop_futures(ctx);
 
@@ -4,14 +4,14 @@ use async_trait::async_trait;
 use futures::{FutureExt, StreamExt};
 use futures_signals::map_ref;
 use futures_signals::signal::{Mutable, SignalExt};
-use glam::UVec2;
+use glam::Vec2;
 use quirky::clone;
 use quirky::drawable_primitive::DrawablePrimitive;
-use quirky::quirky_app_context::QuirkyAppContext;
-use quirky::render_contexts::PrepareContext;
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
 use quirky::widget::{Widget, WidgetBase};
 use quirky::widgets::event_subscribe::run_subscribe_to_events;
 use quirky::widgets::events::{FocusState, KeyCode, KeyboardEvent, MouseEvent, WidgetEvent};
+use quirky::LayoutBox;
 use quirky_macros::widget;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -76,8 +76,8 @@ impl<
             .set(Arc::new([Quad::new(bb.pos, bb.size, color)]));
 
         self.border_box_data.set(BorderBoxData {
-            pos: *bb.pos.as_vec2().as_ref(),
-            size: *bb.size.as_vec2().as_ref(),
+            pos: bb.pos.to_array(),
+            size: bb.size.to_array(),
             color: border_color,
             shade_color: [0.0, 0.0, 0.0, 0.0],
             border_side: 0,
@@ -108,22 +108,22 @@ impl<
 {
     fn prepare(
         &self,
-        quirky_context: &QuirkyAppContext,
-        _paint_ctx: &mut PrepareContext,
+        _quirky_context: &QuirkyAppContext,
+        _resources: &mut QuirkyResources,
     ) -> Vec<Box<dyn DrawablePrimitive>> {
         self.regenerate_primitives();
 
-        let quads = Box::new(Quads::new(
-            self.quad_geometry.read_only(),
-            &quirky_context.device,
-        ));
-
-        let border_box = BorderBox::new(self.border_box_data.read_only(), &quirky_context.device);
+        let quads = Box::new(Quads::new(self.quad_geometry.read_only()));
+        let border_box = BorderBox::new(self.border_box_data.read_only());
 
         vec![quads, Box::new(border_box)]
     }
 
-    fn get_widget_at(&self, pos: UVec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    fn clip_rect(&self) -> Option<LayoutBox> {
+        Some(self.bounding_box.get())
+    }
+
+    fn get_widget_at(&self, pos: Vec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
         if self.bounding_box.get().contains(pos) {
             path.push(self.id);
             Some(path)
@@ -142,6 +142,7 @@ impl<
         }
         .for_each(clone!(self, move |_| clone!(self, async move {
             self.regenerate_primitives();
+            self.set_dirty();
             ctx.signal_redraw().await;
         })));
 
@@ -1,21 +1,17 @@
 use crate::primitives::text::TextRendererPrimitive;
-use crate::resources::font_resource::FontResource;
+use crate::resources::font_resource::FontSystemPool;
 use async_trait::async_trait;
 use futures::{FutureExt, StreamExt};
 use futures_signals::signal::{always, Signal, SignalExt};
-use glam::{uvec2, UVec2};
-use glyphon::{
-    Attrs, Buffer, Color, Family, Metrics, Resolution, Shaping, TextArea, TextBounds, TextRenderer,
-};
+use glam::{vec2, Vec2};
+use glyphon::{Attrs, Buffer, Color, Family, Metrics, Shaping};
 use quirky::drawable_primitive::DrawablePrimitive;
-use quirky::quirky_app_context::QuirkyAppContext;
-use quirky::render_contexts::PrepareContext;
+use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources, SharedResources};
 use quirky::widget::SizeConstraint;
 use quirky::widget::WidgetBase;
 use quirky::widget::{Event, Widget};
 use quirky_macros::widget;
-use std::borrow::BorrowMut;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
 #[widget]
@@ -29,7 +25,8 @@ pub struct TextLayout {
     text: Arc<str>,
     #[slot]
     on_event: Event,
-    text_buffer: Mutex<Option<Buffer>>,
+    #[default(Arc::new(RwLock::new(None)))]
+    text_buffer: Arc<RwLock<Option<Buffer>>>,
 }
 
 #[async_trait]
@@ -41,104 +38,68 @@ impl<
         OnEventCallback: Fn(Event) -> () + Send + Sync,
     > Widget for TextLayout<ColorSignal, ColorSignalFn, TextSignal, TextSignalFn, OnEventCallback>
 {
+    fn prepare_parallel(&self, _ctx: &QuirkyAppContext, resources: &SharedResources) {
+        let font_systems = resources.get_resource::<FontSystemPool>().unwrap();
+
+        let bb = self.bounding_box.get();
+        let mut buffer_lock = self.text_buffer.write().unwrap();
+
+        let buffer = font_systems.with_font_system(|font_system| {
+            if let Some(mut buf) = buffer_lock.take() {
+                buf.set_size(font_system, bb.size.x, bb.size.y);
+
+                buf.set_text(
+                    font_system,
+                    &self.text_prop_value.get_cloned().unwrap(),
+                    Attrs::new().family(Family::SansSerif),
+                    Shaping::Advanced,
+                );
+                buf.shape_until_scroll(font_system);
+                buf
+            } else {
+                let mut buffer = Buffer::new(
+                    font_system,
+                    Metrics {
+                        font_size: 15.0,
+                        line_height: 17.0,
+                    },
+                );
+
+                buffer.set_size(font_system, bb.size.x, bb.size.y);
+
+                buffer.set_text(
+                    font_system,
+                    &self.text_prop_value.get_cloned().unwrap(),
+                    Attrs::new().family(Family::SansSerif),
+                    Shaping::Advanced,
+                );
+
+                buffer.shape_until_scroll(font_system);
+
+                buffer
+            }
+        });
+
+        *buffer_lock = Some(buffer);
+    }
+
     fn prepare(
         &self,
-        ctx: &QuirkyAppContext,
-        paint_ctx: &mut PrepareContext,
+        _ctx: &QuirkyAppContext,
+        _resources: &mut QuirkyResources,
     ) -> Vec<Box<dyn DrawablePrimitive>> {
-        let font_resource = paint_ctx
-            .resources
-            .get_resource_mut::<FontResource>(std::any::TypeId::of::<FontResource>())
-            .unwrap();
-
-        let bb = self.bounding_box.get();
-        let mut buffer_lock = self.text_buffer.lock().unwrap();
-
-        let buffer = if let Some(mut buf) = buffer_lock.take() {
-            buf.set_size(
-                &mut font_resource.font_system,
-                bb.size.x as f32,
-                bb.size.y as f32,
-            );
-
-            buf.set_text(
-                &mut font_resource.font_system,
-                &self.text_prop_value.get_cloned().unwrap(),
-                Attrs::new().family(Family::SansSerif),
-                Shaping::Advanced,
-            );
-            buf.shape_until_scroll(&mut font_resource.font_system);
-            buf
-        } else {
-            let mut buffer = Buffer::new(
-                &mut font_resource.font_system,
-                Metrics {
-                    font_size: 15.0,
-                    line_height: 17.0,
-                },
-            );
-
-            buffer.set_size(
-                &mut font_resource.font_system,
-                bb.size.x as f32,
-                bb.size.y as f32,
-            );
-
-            buffer.set_text(
-                &mut font_resource.font_system,
-                &self.text_prop_value.get_cloned().unwrap(),
-                Attrs::new().family(Family::SansSerif),
-                Shaping::Advanced,
-            );
-
-            buffer.shape_until_scroll(&mut font_resource.font_system);
-
-            buffer
-        };
-
-        let mut renderer = TextRenderer::new(
-            &mut font_resource.text_atlas.borrow_mut(),
-            &ctx.device,
-            Default::default(),
-            None,
-        );
-
-        let screen_resolution = ctx.viewport_size.get();
-        let buffer = buffer_lock.insert(buffer);
-
-        let _ = renderer.prepare(
-            &ctx.device,
-            &ctx.queue,
-            &mut font_resource.font_system.borrow_mut(),
-            &mut font_resource.text_atlas.borrow_mut(),
-            Resolution {
-                width: screen_resolution.x,
-                height: screen_resolution.y,
-            },
-            [TextArea {
-                buffer,
-                left: bb.pos.x as f32,
-                top: bb.pos.y as f32,
-                scale: 1.0,
-                bounds: TextBounds {
-                    left: bb.pos.x as i32,
-                    top: bb.pos.y as i32,
-                    right: (bb.pos.x as i32 + bb.size.x as i32).min(screen_resolution.x as i32),
-                    bottom: (bb.pos.y as i32 + bb.size.y as i32).min(screen_resolution.y as i32),
-                },
-                default_color: Color::rgb(80, 80, 50),
-            }],
-            &mut font_resource.font_cache.borrow_mut(),
-        );
-
-        vec![Box::new(TextRendererPrimitive(renderer))]
+        vec![Box::new(TextRendererPrimitive::new(
+            self.text_buffer.clone(),
+            self.bounding_box.get(),
+            Color::rgb(80, 80, 50),
+        ))]
     }
 
     fn size_constraint(&self) -> Box<dyn Signal<Item = SizeConstraint> + Unpin + Send> {
-        Box::new(always(SizeConstraint::MinSize(uvec2(10, 10))))
+        Box::new(always(SizeConstraint::MinSize(vec2(10.0, 10.0))))
     }
 
-    fn get_widget_at(&self, _pos: UVec2, _path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    fn get_widget_at(&self, _pos: Vec2, _path: Vec<Uuid>) -> Option<Vec<Uuid>> {
         None
     }
 
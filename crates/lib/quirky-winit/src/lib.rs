@@ -1,15 +1,13 @@
 use futures_signals::signal::{Mutable, SignalExt};
 use glam::UVec2;
+use quirky::input_driver::InputDriver;
 use quirky::quirky_app_context::{QuirkyAppContext, QuirkyResources};
 use quirky::widget::Widget;
-use quirky::widgets::events::{
-    KeyCode, KeyboardEvent, KeyboardModifier, MouseButton, MouseEvent, WidgetEvent,
-};
+use quirky::widgets::events::{KeyCode, KeyboardModifier, MouseButton};
 use quirky::{clone, QuirkyApp};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::time::sleep;
-use uuid::Uuid;
 use wgpu::{
     Backends, Instance, InstanceDescriptor, PresentMode, Surface, SurfaceCapabilities,
     TextureFormat,
@@ -26,6 +24,7 @@ pub struct QuirkyWinitApp {
     pub surface_format: TextureFormat,
     surface_capabilities: SurfaceCapabilities,
     window: Window,
+    debug_overlay_key: Option<KeyCode>,
 }
 
 impl QuirkyWinitApp {
@@ -79,6 +78,8 @@ impl QuirkyWinitApp {
             ui_factory,
         ));
 
+        quirky_app.scale_factor.set(window.scale_factor());
+
         let quirky_winit_app = Self {
             quirky_app: quirky_app.clone(),
             event_loop: Some(event_loop),
@@ -86,11 +87,20 @@ impl QuirkyWinitApp {
             surface_format,
             surface_capabilities,
             window,
+            debug_overlay_key: None,
         };
 
         Ok((quirky_winit_app, quirky_app))
     }
 
+    /// Toggles the debug overlay with `key`, which is then no longer passed on to the app.
+    ///
+    /// No key toggles the overlay by default.
+    pub fn with_debug_overlay_key(mut self, key: Option<KeyCode>) -> Self {
+        self.debug_overlay_key = key;
+        self
+    }
+
     pub fn get_trigger_draw_callback(&self) -> impl Fn() {
         let elproxy = self.event_loop.as_ref().unwrap().create_proxy();
 
@@ -107,75 +117,22 @@ impl QuirkyWinitApp {
             .take()
             .expect("invalid QuirkiWinitApp: missing event loop");
 
-        let prev_hovered: Mutable<Option<Uuid>> = Default::default();
-        let target_widget: Mutable<Option<Uuid>> = Default::default();
-        let prev_drag_pos: Mutable<Option<UVec2>> = Default::default();
-        let drag_button: Mutable<Option<MouseButton>> = Default::default();
+        let input = Arc::new(Mutex::new(InputDriver::new(self.quirky_app.clone())));
         let mut modifiers = KeyboardModifier::default();
         let current_mouse_pos: Mutable<UVec2> = Default::default();
 
-        let quirky_app = self.quirky_app.clone();
         tokio::spawn(clone!(
-            prev_hovered,
-            clone!(
-                drag_button,
-                clone!(
-                    target_widget,
-                    clone!(
-                        prev_drag_pos,
-                        clone!(current_mouse_pos, async move {
-                            current_mouse_pos
-                                .signal()
-                                .throttle(|| sleep(Duration::from_millis(5)))
-                                .for_each(|pos| {
-                                    if target_widget.get().is_some()
-                                        && prev_drag_pos.get().is_some()
-                                    {
-                                        quirky_app.dispatch_event_to_widget(
-                                            target_widget.get().unwrap(),
-                                            WidgetEvent::MouseEvent {
-                                                event: MouseEvent::Drag {
-                                                    from: prev_drag_pos.get().unwrap(),
-                                                    to: pos,
-                                                    button: drag_button.get().unwrap(),
-                                                },
-                                            },
-                                        );
-                                    }
-
-                                    if let Some(widgets) = quirky_app.get_widgets_at(pos) {
-                                        let new_target_widget = *widgets.first().unwrap();
-
-                                        if let Some(p) = prev_hovered.get() {
-                                            if p != new_target_widget {
-                                                quirky_app.dispatch_event_to_widget(
-                                                    p,
-                                                    WidgetEvent::MouseEvent {
-                                                        event: MouseEvent::Leave {},
-                                                    },
-                                                );
-                                            }
-                                        }
-
-                                        prev_hovered.set(Some(new_target_widget));
-
-                                        quirky_app.dispatch_event_to_widget(
-                                            new_target_widget,
-                                            WidgetEvent::MouseEvent {
-                                                event: MouseEvent::Move { pos },
-                                            },
-                                        );
-                                    }
-
-                                    prev_drag_pos.set(Some(pos));
-
-                                    async move {}
-                                })
-                                .await;
-                        })
-                    )
-                )
-            )
+            input,
+            clone!(current_mouse_pos, async move {
+                current_mouse_pos
+                    .signal()
+                    .throttle(|| sleep(Duration::from_millis(5)))
+                    .for_each(|pos| {
+                        input.lock().unwrap().move_to(pos);
+                        async move {}
+                    })
+                    .await;
+            })
         ));
 
         event_loop.run(move |event, _target, control_flow: &mut ControlFlow| {
@@ -205,78 +162,40 @@ impl QuirkyWinitApp {
                         modifiers.shift = state.shift();
                         modifiers.ctrl = state.ctrl();
                     }
-                    WindowEvent::KeyboardInput { input, .. } => {
-                        if input.state == ElementState::Pressed {
-                            let target = prev_hovered.get().unwrap_or(Uuid::nil());
-
-                            let code = input
+                    WindowEvent::KeyboardInput { input: key, .. } => {
+                        if key.state == ElementState::Pressed {
+                            let code = key
                                 .virtual_keycode
                                 .map(winit_keycode_to_quirky)
                                 .unwrap_or(KeyCode::Unknown);
 
-                            self.quirky_app.dispatch_event_to_widget(
-                                target,
-                                WidgetEvent::KeyboardEvent {
-                                    event: KeyboardEvent::KeyPressed {
-                                        key_code: code,
-                                        modifier: modifiers.clone(),
-                                    },
-                                },
-                            )
+                            if Some(code) == self.debug_overlay_key {
+                                let overlay = &self.quirky_app.debug_overlay_enabled;
+                                overlay.set(!overlay.get());
+                            } else {
+                                input.lock().unwrap().press(code, modifiers.clone());
+                            }
                         }
                     }
                     WindowEvent::Resized(new_size) => self.resize_window(new_size),
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    WindowEvent::MouseInput { state, button, .. } => {
-                        let mouse_pos = current_mouse_pos.get();
-
-                        if state == ElementState::Pressed {
-                            if let Some(p) = self
-                                .quirky_app
-                                .get_widgets_at(UVec2::new(mouse_pos.x, mouse_pos.y))
-                            {
-                                prev_drag_pos.set(Some(UVec2::new(mouse_pos.x, mouse_pos.y)));
-
-                                let new_target_widget = *p.first().unwrap();
-
-                                target_widget.set(Some(new_target_widget));
-
-                                if button == winit::event::MouseButton::Left {
-                                    drag_button.set(Some(MouseButton::Left));
-                                } else if button == winit::event::MouseButton::Right {
-                                    drag_button.set(Some(MouseButton::Right));
-                                } else if button == winit::event::MouseButton::Middle {
-                                    drag_button.set(Some(MouseButton::Middle));
-                                }
-
-                                if let Some(b) = &drag_button.get() {
-                                    self.quirky_app.dispatch_event_to_widget(
-                                        new_target_widget,
-                                        WidgetEvent::MouseEvent {
-                                            event: MouseEvent::ButtonDown { button: *b },
-                                        },
-                                    );
-                                }
-                            }
-                        }
-
-                        if state == ElementState::Released {
-                            if let Some(prev_target_widget) = target_widget.get() {
-                                self.quirky_app.dispatch_event_to_widget(
-                                    prev_target_widget,
-                                    WidgetEvent::MouseEvent {
-                                        event: MouseEvent::ButtonUp {
-                                            button: drag_button.get().unwrap(),
-                                        },
-                                    },
-                                );
-                            }
-
-                            drag_button.set(None);
-                            target_widget.set(None);
-                        }
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => {
+                        self.quirky_app.scale_factor.set(scale_factor);
+                        self.resize_window(*new_inner_size);
                     }
+                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    WindowEvent::MouseInput { state, button, .. } => match state {
+                        ElementState::Pressed => input
+                            .lock()
+                            .unwrap()
+                            .button_down(current_mouse_pos.get(), winit_button_to_quirky(button)),
+                        ElementState::Released => input.lock().unwrap().button_up(),
+                    },
                     WindowEvent::CursorMoved { position, .. } => {
+                        let position =
+                            position.to_logical::<f64>(self.quirky_app.scale_factor.get());
                         let pos = UVec2::new(position.x as u32, position.y as u32);
                         current_mouse_pos.set(pos);
                     }
@@ -299,14 +218,23 @@ impl QuirkyWinitApp {
                 view_formats: vec![],
             };
 
-            self.surface
-                .configure(&self.quirky_app.context.device, &config);
+            self.surface.configure(&self.quirky_app.device, &config);
             self.quirky_app
                 .viewport_size
                 .set(UVec2::new(new_size.width, new_size.height));
         }
     }
 }
+
+fn winit_button_to_quirky(button: winit::event::MouseButton) -> MouseButton {
+    match button {
+        winit::event::MouseButton::Left => MouseButton::Left,
+        winit::event::MouseButton::Right => MouseButton::Right,
+        winit::event::MouseButton::Middle => MouseButton::Middle,
+        winit::event::MouseButton::Other(n) => MouseButton::Num(n as usize),
+    }
+}
+
 fn winit_keycode_to_quirky(keycode: VirtualKeyCode) -> KeyCode {
     match keycode {
         VirtualKeyCode::Key1 => KeyCode::Key1,
@@ -346,6 +274,18 @@ fn winit_keycode_to_quirky(keycode: VirtualKeyCode) -> KeyCode {
         VirtualKeyCode::Y => KeyCode::Y,
         VirtualKeyCode::Z => KeyCode::Z,
         VirtualKeyCode::Escape => KeyCode::Escape,
+        VirtualKeyCode::F1 => KeyCode::F1,
+        VirtualKeyCode::F2 => KeyCode::F2,
+        VirtualKeyCode::F3 => KeyCode::F3,
+        VirtualKeyCode::F4 => KeyCode::F4,
+        VirtualKeyCode::F5 => KeyCode::F5,
+        VirtualKeyCode::F6 => KeyCode::F6,
+        VirtualKeyCode::F7 => KeyCode::F7,
+        VirtualKeyCode::F8 => KeyCode::F8,
+        VirtualKeyCode::F9 => KeyCode::F9,
+        VirtualKeyCode::F10 => KeyCode::F10,
+        VirtualKeyCode::F11 => KeyCode::F11,
+        VirtualKeyCode::F12 => KeyCode::F12,
         VirtualKeyCode::Back => KeyCode::Backspace,
         VirtualKeyCode::Return => KeyCode::Return,
         VirtualKeyCode::Space => KeyCode::Space,
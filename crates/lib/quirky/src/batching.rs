@@ -0,0 +1,374 @@
+use crate::drawable_primitive::DrawablePrimitive;
+use crate::frame_stats::FrameStats;
+use crate::pipeline_registry::PipelineId;
+use crate::render_contexts::RenderContext;
+use crate::LayoutBox;
+use wgpu::{Buffer, Device, Queue, RenderPass};
+
+/// A primitive to draw, along with the clip rect and camera group of its widget
+#[derive(Clone, Copy)]
+pub(crate) struct ClippedPrimitive<'a> {
+    pub primitive: &'a dyn DrawablePrimitive,
+    pub clip: Option<LayoutBox>,
+    /// The render context drawing the primitive, see [`crate::camera_groups::CameraGroups`]
+    pub camera: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum DrawStep {
+    /// A primitive drawing itself with [`DrawablePrimitive::draw`]
+    Single(usize),
+    /// Consecutive primitives drawn with one call, through the first one of the run
+    Batch {
+        first: usize,
+        pipeline: PipelineId,
+        offset: u64,
+        size: u64,
+        instances: u32,
+    },
+}
+
+/// Merges the instances of consecutive primitives sharing a pipeline into one frame wide
+/// instance buffer, so that each run is drawn with a single call.
+///
+/// Draw order is preserved: a primitive that cannot be batched, uses another pipeline, has
+/// another clip rect or another camera ends the current run.
+#[derive(Default)]
+pub(crate) struct InstanceBatcher {
+    data: Vec<u8>,
+    steps: Vec<DrawStep>,
+    buffer: Option<Buffer>,
+    buffer_capacity: u64,
+}
+
+impl InstanceBatcher {
+    /// Groups `primitives` into draw steps and collects the instances of the batched ones.
+    pub fn plan(&mut self, primitives: &[ClippedPrimitive]) {
+        self.data.clear();
+        self.steps.clear();
+
+        for (index, item) in primitives.iter().enumerate() {
+            let primitive = item.primitive;
+
+            let Some(pipeline) = primitive.pipeline_id() else {
+                self.steps.push(DrawStep::Single(index));
+                continue;
+            };
+
+            let continues_batch = matches!(
+                self.steps.last(),
+                Some(DrawStep::Batch { first, pipeline: current, .. })
+                    if *current == pipeline
+                        && primitives[*first].clip == item.clip
+                        && primitives[*first].camera == item.camera
+            );
+
+            if !continues_batch {
+                // Vertex buffer offsets need to be aligned
+                self.data.resize(
+                    align_to(self.data.len() as u64, wgpu::VERTEX_STRIDE_ALIGNMENT) as usize,
+                    0,
+                );
+            }
+
+            let start = self.data.len();
+
+            let Some(instances) = primitive.write_instances(&mut self.data) else {
+                self.data.truncate(start);
+                self.steps.push(DrawStep::Single(index));
+                continue;
+            };
+
+            if instances == 0 {
+                continue;
+            }
+
+            let written = (self.data.len() - start) as u64;
+
+            match self.steps.last_mut() {
+                Some(DrawStep::Batch {
+                    size,
+                    instances: batch_instances,
+                    ..
+                }) if continues_batch => {
+                    *size += written;
+                    *batch_instances += instances;
+                }
+                _ => self.steps.push(DrawStep::Batch {
+                    first: index,
+                    pipeline,
+                    offset: start as u64,
+                    size: written,
+                    instances,
+                }),
+            }
+        }
+    }
+
+    /// Uploads the instances collected by [`InstanceBatcher::plan`], growing the buffer if needed.
+    pub fn upload(&mut self, device: &Device, queue: &Queue) {
+        if self.data.is_empty() {
+            return;
+        }
+
+        self.data.resize(
+            align_to(self.data.len() as u64, wgpu::COPY_BUFFER_ALIGNMENT) as usize,
+            0,
+        );
+
+        let size = self.data.len() as u64;
+
+        if self.buffer.is_none() || self.buffer_capacity < size {
+            self.buffer_capacity = size.next_power_of_two();
+            self.buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("batched instance buffer"),
+                size: self.buffer_capacity,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+
+        queue.write_buffer(self.buffer.as_ref().unwrap(), 0, &self.data);
+    }
+
+    /// Records the planned draws, restricted to `scissor` and the clip rects of the primitives.
+    ///
+    /// The scissor rect of `pass` is expected to be `scissor` already. `render_contexts` holds a
+    /// context per camera group of the primitives.
+    pub fn draw<'a>(
+        &'a self,
+        pass: &mut RenderPass<'a>,
+        render_contexts: &'a [RenderContext<'a>],
+        primitives: &[ClippedPrimitive<'a>],
+        scissor: LayoutBox,
+        stats: &mut FrameStats,
+    ) {
+        let mut current_pipeline = None;
+        let mut current_scissor = scissor;
+
+        for step in self.steps.iter() {
+            let first = match step {
+                DrawStep::Single(index) => *index,
+                DrawStep::Batch { first, .. } => *first,
+            };
+
+            let step_scissor = match primitives[first].clip {
+                Some(clip) => clip.intersect(&scissor),
+                None => Some(scissor),
+            };
+
+            // Clipped away entirely
+            let Some(step_scissor) = step_scissor else {
+                continue;
+            };
+
+            if step_scissor != current_scissor {
+                let (x, y, width, height) = step_scissor.scissor_rect();
+
+                pass.set_scissor_rect(x, y, width, height);
+                current_scissor = step_scissor;
+            }
+
+            let render_context = &render_contexts[primitives[first].camera];
+
+            let pipeline = match step {
+                DrawStep::Single(index) => primitives[*index].primitive.pipeline_id(),
+                DrawStep::Batch { pipeline, .. } => Some(*pipeline),
+            };
+
+            if pipeline.is_none() || pipeline != current_pipeline {
+                stats.pipeline_switches += 1;
+            }
+
+            current_pipeline = pipeline;
+
+            let _span = tracing::trace_span!("primitive_draw").entered();
+
+            match step {
+                DrawStep::Single(index) => primitives[*index].primitive.draw(pass, render_context),
+                DrawStep::Batch {
+                    first,
+                    offset,
+                    size,
+                    instances,
+                    ..
+                } => {
+                    stats.batches += 1;
+
+                    primitives[*first].primitive.draw_instanced(
+                        pass,
+                        render_context,
+                        self.buffer
+                            .as_ref()
+                            .expect("batched instances were not uploaded")
+                            .slice(*offset..*offset + *size),
+                        *instances,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn align_to(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod test {
+    use crate::batching::{ClippedPrimitive, DrawStep, InstanceBatcher};
+    use crate::drawable_primitive::DrawablePrimitive;
+    use crate::pipeline_registry::test::{PipelineA, PipelineB};
+    use crate::pipeline_registry::{PipelineId, PipelineTarget};
+    use crate::render_contexts::RenderContext;
+    use crate::LayoutBox;
+    use wgpu::TextureFormat;
+
+    const TARGET: PipelineTarget = PipelineTarget {
+        format: TextureFormat::Bgra8UnormSrgb,
+        sample_count: 1,
+    };
+
+    fn pipeline_a() -> PipelineId {
+        PipelineId::of::<PipelineA>(TARGET)
+    }
+
+    fn pipeline_b() -> PipelineId {
+        PipelineId::of::<PipelineB>(TARGET)
+    }
+
+    struct FakePrimitive {
+        pipeline: Option<PipelineId>,
+        instances: Option<Vec<u32>>,
+    }
+
+    impl DrawablePrimitive for FakePrimitive {
+        fn draw<'a>(&'a self, _pass: &mut wgpu::RenderPass<'a>, _ctx: &'a RenderContext<'a>) {}
+
+        fn pipeline_id(&self) -> Option<PipelineId> {
+            self.pipeline
+        }
+
+        fn write_instances(&self, out: &mut Vec<u8>) -> Option<u32> {
+            let instances = self.instances.as_ref()?;
+            out.extend_from_slice(bytemuck::cast_slice(instances));
+            Some(instances.len() as u32)
+        }
+    }
+
+    fn batchable(pipeline: PipelineId, instances: &[u32]) -> FakePrimitive {
+        FakePrimitive {
+            pipeline: Some(pipeline),
+            instances: Some(instances.to_vec()),
+        }
+    }
+
+    #[test]
+    fn consecutive_primitives_sharing_a_pipeline_are_merged_in_order() {
+        let primitives = [
+            batchable(pipeline_a(), &[1, 2]),
+            batchable(pipeline_a(), &[]),
+            batchable(pipeline_a(), &[3]),
+            FakePrimitive {
+                pipeline: Some(pipeline_a()),
+                instances: None,
+            },
+            batchable(pipeline_a(), &[4]),
+            batchable(pipeline_b(), &[5]),
+        ];
+
+        let primitives: Vec<ClippedPrimitive> = primitives
+            .iter()
+            .map(|p| ClippedPrimitive {
+                primitive: p as &dyn DrawablePrimitive,
+                clip: None,
+                camera: 0,
+            })
+            .collect();
+
+        let mut batcher = InstanceBatcher::default();
+        batcher.plan(&primitives);
+
+        assert_eq!(
+            batcher.steps,
+            vec![
+                DrawStep::Batch {
+                    first: 0,
+                    pipeline: pipeline_a(),
+                    offset: 0,
+                    size: 12,
+                    instances: 3,
+                },
+                DrawStep::Single(3),
+                DrawStep::Batch {
+                    first: 4,
+                    pipeline: pipeline_a(),
+                    offset: 12,
+                    size: 4,
+                    instances: 1,
+                },
+                DrawStep::Batch {
+                    first: 5,
+                    pipeline: pipeline_b(),
+                    offset: 16,
+                    size: 4,
+                    instances: 1,
+                },
+            ]
+        );
+        let instances: Vec<u32> = batcher
+            .data
+            .chunks(4)
+            .map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap()))
+            .collect();
+
+        assert_eq!(instances, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn differently_clipped_primitives_are_not_merged() {
+        let (a, b) = (batchable(pipeline_a(), &[1]), batchable(pipeline_a(), &[2]));
+        let clip = Some(LayoutBox::default());
+
+        let primitives = [
+            ClippedPrimitive {
+                primitive: &a,
+                clip: None,
+                camera: 0,
+            },
+            ClippedPrimitive {
+                primitive: &b,
+                clip,
+                camera: 0,
+            },
+        ];
+
+        let mut batcher = InstanceBatcher::default();
+        batcher.plan(&primitives);
+
+        assert_eq!(batcher.steps.len(), 2);
+    }
+
+    #[test]
+    fn differently_transformed_primitives_are_not_merged() {
+        let (a, b) = (batchable(pipeline_a(), &[1]), batchable(pipeline_a(), &[2]));
+
+        let primitives = [
+            ClippedPrimitive {
+                primitive: &a,
+                clip: None,
+                camera: 0,
+            },
+            ClippedPrimitive {
+                primitive: &b,
+                clip: None,
+                camera: 1,
+            },
+        ];
+
+        let mut batcher = InstanceBatcher::default();
+        batcher.plan(&primitives);
+
+        assert_eq!(batcher.steps.len(), 2);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::ui_camera::UiCamera2D;
+use glam::Affine2;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Device, Queue};
+
+struct CameraGroup {
+    buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+/// The distinct widget transforms of a frame, each drawn with its own camera uniform.
+///
+/// The group at index 0 is always the untransformed camera. Buffers and bind groups are kept
+/// across frames, so they are only created when a frame has more transforms than any before.
+#[derive(Default)]
+pub(crate) struct CameraGroups {
+    transforms: Vec<Affine2>,
+    groups: Vec<CameraGroup>,
+}
+
+impl CameraGroups {
+    pub fn clear(&mut self) {
+        self.transforms.clear();
+        self.transforms.push(Affine2::IDENTITY);
+    }
+
+    /// The index of the group drawing with `transform`, adding one if needed
+    pub fn group_for(&mut self, transform: Affine2) -> usize {
+        match self.transforms.iter().position(|t| *t == transform) {
+            Some(index) => index,
+            None => {
+                self.transforms.push(transform);
+                self.transforms.len() - 1
+            }
+        }
+    }
+
+    /// Writes the camera uniform of every group, creating the missing buffers.
+    pub fn upload(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        layout: &BindGroupLayout,
+        camera: &UiCamera2D,
+    ) {
+        while self.groups.len() < self.transforms.len() {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("camera group buffer"),
+                size: std::mem::size_of::<crate::ui_camera::UiCameraUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("camera group"),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+
+            self.groups.push(CameraGroup { buffer, bind_group });
+        }
+
+        for (transform, group) in self.transforms.iter().zip(self.groups.iter()) {
+            queue.write_buffer(
+                &group.buffer,
+                0,
+                bytemuck::cast_slice(&[camera.create_transformed_camera_uniform(*transform)]),
+            );
+        }
+    }
+
+    /// The bind groups of this frame's groups, in group order
+    pub fn bind_groups(&self) -> impl Iterator<Item = &BindGroup> {
+        self.groups
+            .iter()
+            .take(self.transforms.len())
+            .map(|group| &group.bind_group)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::camera_groups::CameraGroups;
+    use glam::{vec2, Affine2};
+
+    #[test]
+    fn identical_transforms_share_a_group() {
+        let mut groups = CameraGroups::default();
+        groups.clear();
+
+        let moved = Affine2::from_translation(vec2(10.0, 0.0));
+        let scaled = Affine2::from_scale(vec2(2.0, 2.0));
+
+        assert_eq!(groups.group_for(Affine2::IDENTITY), 0);
+        assert_eq!(groups.group_for(moved), 1);
+        assert_eq!(groups.group_for(scaled), 2);
+        assert_eq!(groups.group_for(moved), 1);
+
+        groups.clear();
+
+        assert_eq!(groups.group_for(scaled), 1);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::LayoutBox;
+
+/// The clip rects of the containers enclosing the widget being visited, each one already
+/// intersected with the ones below it.
+#[derive(Default)]
+pub(crate) struct ClipStack {
+    stack: Vec<LayoutBox>,
+}
+
+impl ClipStack {
+    pub fn push(&mut self, clip: LayoutBox) {
+        let clip = match self.current() {
+            Some(current) => current.intersect(&clip).unwrap_or(LayoutBox {
+                pos: clip.pos,
+                size: Default::default(),
+            }),
+            None => clip,
+        };
+
+        self.stack.push(clip);
+    }
+
+    pub fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    /// The area drawing is restricted to, `None` when nothing clips
+    pub fn current(&self) -> Option<LayoutBox> {
+        self.stack.last().copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::clip_stack::ClipStack;
+    use crate::LayoutBox;
+    use glam::vec2;
+
+    #[test]
+    fn nested_clips_intersect() {
+        let mut clips = ClipStack::default();
+
+        assert_eq!(clips.current(), None);
+
+        clips.push(LayoutBox {
+            pos: vec2(0.0, 0.0),
+            size: vec2(100.0, 100.0),
+        });
+        clips.push(LayoutBox {
+            pos: vec2(50.0, 50.0),
+            size: vec2(100.0, 100.0),
+        });
+
+        assert_eq!(
+            clips.current(),
+            Some(LayoutBox {
+                pos: vec2(50.0, 50.0),
+                size: vec2(50.0, 50.0),
+            })
+        );
+
+        clips.push(LayoutBox {
+            pos: vec2(200.0, 200.0),
+            size: vec2(10.0, 10.0),
+        });
+
+        assert!(clips.current().unwrap().is_empty());
+
+        clips.pop();
+        clips.pop();
+
+        assert_eq!(clips.current().unwrap().size, vec2(100.0, 100.0));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::LayoutBox;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Collects the area of the screen that changed since the last frame.
+///
+/// A widget damages both the box it is drawn at and the box it was last drawn at when it is
+/// dirty or has moved, and a removed widget damages its last drawn box. A widget that moved
+/// without being dirty still draws its old primitives, so it keeps the box it was last drawn at
+/// until it is dirty. Widgets that are not visited keep the box they were last drawn at.
+#[derive(Default)]
+pub(crate) struct DamageTracker {
+    drawn_boxes: HashMap<Uuid, LayoutBox>,
+    damage: Option<LayoutBox>,
+}
+
+impl DamageTracker {
+    pub fn add(&mut self, bb: LayoutBox) {
+        if bb.is_empty() {
+            return;
+        }
+
+        self.damage = Some(match self.damage {
+            Some(damage) => damage.union(&bb),
+            None => bb,
+        });
+    }
+
+    pub fn visit(&mut self, widget_id: Uuid, bb: LayoutBox, dirty: bool) {
+        let previous = if dirty {
+            self.drawn_boxes.insert(widget_id, bb)
+        } else {
+            self.drawn_boxes.get(&widget_id).copied()
+        };
+
+        if dirty || previous != Some(bb) {
+            self.add(bb);
+
+            if let Some(previous) = previous {
+                self.add(previous);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, widget_id: Uuid) {
+        if let Some(previous) = self.drawn_boxes.remove(&widget_id) {
+            self.add(previous);
+        }
+    }
+
+    /// The damage collected since the last call
+    pub fn finish(&mut self) -> Option<LayoutBox> {
+        self.damage.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::damage::DamageTracker;
+    use crate::LayoutBox;
+    use glam::vec2;
+    use uuid::Uuid;
+
+    fn bb(x: f32, y: f32, w: f32, h: f32) -> LayoutBox {
+        LayoutBox {
+            pos: vec2(x, y),
+            size: vec2(w, h),
+        }
+    }
+
+    #[test]
+    fn boxes_union_and_intersect() {
+        assert_eq!(
+            bb(0.0, 0.0, 10.0, 10.0).union(&bb(20.0, 5.0, 10.0, 10.0)),
+            bb(0.0, 0.0, 30.0, 15.0)
+        );
+        assert_eq!(
+            bb(0.0, 0.0, 10.0, 10.0).intersect(&bb(5.0, 5.0, 10.0, 10.0)),
+            Some(bb(5.0, 5.0, 5.0, 5.0))
+        );
+        assert_eq!(
+            bb(0.0, 0.0, 10.0, 10.0).intersect(&bb(10.0, 0.0, 10.0, 10.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn boxes_cover_their_physical_pixels() {
+        assert_eq!(
+            bb(10.0, 20.0, 30.0, 40.0).to_physical(2.0),
+            bb(20.0, 40.0, 60.0, 80.0)
+        );
+        assert_eq!(
+            bb(1.0, 1.0, 1.0, 1.0).to_physical(1.5),
+            bb(1.0, 1.0, 2.0, 2.0)
+        );
+        assert_eq!(
+            bb(0.4, 0.0, 9.2, 1.0).to_physical(1.0),
+            bb(0.0, 0.0, 10.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn boxes_snap_to_the_nearest_pixels() {
+        assert_eq!(
+            bb(0.4, 0.6, 9.2, 9.8).snap_to_pixels(1.0),
+            bb(0.0, 1.0, 10.0, 9.0)
+        );
+        assert_eq!(
+            bb(0.3, 0.0, 1.0, 1.0).snap_to_pixels(2.0),
+            bb(0.5, 0.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn only_changed_widgets_are_damaged() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut tracker = DamageTracker::default();
+
+        tracker.visit(a, bb(0.0, 0.0, 10.0, 10.0), true);
+        tracker.visit(b, bb(50.0, 50.0, 10.0, 10.0), true);
+        tracker.visit(c, bb(80.0, 0.0, 10.0, 10.0), true);
+        assert_eq!(tracker.finish(), Some(bb(0.0, 0.0, 90.0, 60.0)));
+
+        tracker.visit(a, bb(0.0, 0.0, 10.0, 10.0), false);
+        tracker.visit(b, bb(50.0, 50.0, 10.0, 10.0), false);
+        tracker.visit(c, bb(80.0, 0.0, 10.0, 10.0), false);
+        assert_eq!(tracker.finish(), None);
+
+        // `b` moves and `c` is removed, `a` is not visited
+        tracker.visit(b, bb(60.0, 50.0, 10.0, 10.0), false);
+        tracker.remove(c);
+        assert_eq!(tracker.finish(), Some(bb(50.0, 0.0, 40.0, 60.0)));
+
+        // `b` is only dirtied on a later frame, its old primitives are still drawn at the old box
+        tracker.visit(b, bb(60.0, 50.0, 10.0, 10.0), true);
+        assert_eq!(tracker.finish(), Some(bb(50.0, 50.0, 20.0, 10.0)));
+
+        tracker.visit(b, bb(60.0, 50.0, 10.0, 10.0), false);
+        assert_eq!(tracker.finish(), None);
+    }
+}
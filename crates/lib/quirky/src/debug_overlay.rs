@@ -0,0 +1,277 @@
+use crate::widget::Widget;
+use crate::LayoutBox;
+use glam::vec2;
+use std::mem;
+use std::sync::Arc;
+use wgpu::{
+    include_wgsl, BindGroup, BindGroupLayout, Buffer, Device, PipelineLayoutDescriptor, Queue,
+    RenderPass, RenderPipeline, TextureFormat, VertexState,
+};
+
+pub(crate) const HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+pub(crate) const HIT_PATH_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const CLEAN_COLOR: [f32; 4] = [0.0, 0.6, 0.0, 0.8];
+const DIRTY_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 0.8];
+/// For widgets reaching outside the box of their parent
+const OVERFLOW_COLOR: [f32; 4] = [1.0, 0.5, 0.0, 0.8];
+/// Size of the marker drawn for widgets without any area
+const EMPTY_MARKER_SIZE: f32 = 6.0;
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, Debug)]
+pub(crate) struct DebugRect {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl DebugRect {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<DebugRect>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Outlines drawn on top of the widget tree, for debugging tools.
+///
+/// The overlay owns its pipeline, so it works without any widget library being initialized.
+#[derive(Default)]
+pub(crate) struct DebugOverlay {
+    rects: Vec<DebugRect>,
+    pipeline: Option<Arc<RenderPipeline>>,
+    instance_buffer: Option<Arc<Buffer>>,
+    instance_capacity: usize,
+    instance_count: u32,
+}
+
+impl DebugOverlay {
+    /// Adds an outline drawn on the inside of `bb`
+    pub fn outline(&mut self, bb: LayoutBox, thickness: f32, color: [f32; 4]) {
+        let (pos, size) = (bb.pos, bb.size);
+
+        if size.x == 0.0 || size.y == 0.0 {
+            self.rects.push(DebugRect {
+                pos: (pos - EMPTY_MARKER_SIZE / 2.0).to_array(),
+                size: [EMPTY_MARKER_SIZE; 2],
+                color,
+            });
+
+            return;
+        }
+
+        let thickness = thickness.min(size.x / 2.0).min(size.y / 2.0);
+
+        let edges = [
+            (pos, [size.x, thickness]),
+            (pos + vec2(0.0, size.y - thickness), [size.x, thickness]),
+            (
+                pos + vec2(0.0, thickness),
+                [thickness, size.y - 2.0 * thickness],
+            ),
+            (
+                pos + vec2(size.x - thickness, thickness),
+                [thickness, size.y - 2.0 * thickness],
+            ),
+        ];
+
+        self.rects
+            .extend(edges.into_iter().map(|(pos, size)| DebugRect {
+                pos: pos.to_array(),
+                size,
+                color,
+            }));
+    }
+
+    /// Outlines `widget` and all of its descendants.
+    ///
+    /// Dirty widgets and widgets overflowing their parent get their own colors, and widgets
+    /// without any area are marked with a small square.
+    pub fn outline_tree(&mut self, widget: &Arc<dyn Widget>, parent_box: Option<LayoutBox>) {
+        let bb = widget.bounding_box().get();
+
+        let color = if widget.dirty().get() {
+            DIRTY_COLOR
+        } else if parent_box.is_some_and(|parent| !contains_box(&parent, &bb)) {
+            OVERFLOW_COLOR
+        } else {
+            CLEAN_COLOR
+        };
+
+        self.outline(bb, 1.0, color);
+
+        if let Some(children) = widget.children() {
+            for child in children.iter() {
+                self.outline_tree(child, Some(bb));
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+
+    pub fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        surface_format: TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+    ) {
+        self.instance_count = self.rects.len() as u32;
+
+        if self.rects.is_empty() {
+            return;
+        }
+
+        if self.pipeline.is_none() {
+            self.pipeline = Some(Arc::new(configure_pipeline(
+                device,
+                surface_format,
+                camera_bind_group_layout,
+            )));
+        }
+
+        if self.instance_buffer.is_none() || self.instance_capacity < self.rects.len() {
+            self.instance_capacity = self.rects.len();
+            self.instance_buffer = Some(Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("debug overlay instance buffer"),
+                size: (self.instance_capacity * mem::size_of::<DebugRect>()) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })));
+        }
+
+        queue.write_buffer(
+            self.instance_buffer.as_ref().unwrap(),
+            0,
+            bytemuck::cast_slice(&self.rects),
+        );
+    }
+
+    /// The prepared outlines, independent of the overlay so that they can be drawn later
+    pub fn prepared(&self) -> Option<PreparedOverlay> {
+        if self.instance_count == 0 {
+            return None;
+        }
+
+        Some(PreparedOverlay {
+            pipeline: self.pipeline.clone()?,
+            instance_buffer: self.instance_buffer.clone()?,
+            instance_count: self.instance_count,
+        })
+    }
+}
+
+pub(crate) struct PreparedOverlay {
+    pipeline: Arc<RenderPipeline>,
+    instance_buffer: Arc<Buffer>,
+    instance_count: u32,
+}
+
+impl PreparedOverlay {
+    pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, camera_bind_group: &'a BindGroup) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        pass.draw(0..6, 0..self.instance_count);
+    }
+}
+
+fn contains_box(outer: &LayoutBox, inner: &LayoutBox) -> bool {
+    let outer_end = outer.pos + outer.size;
+    let inner_end = inner.pos + inner.size;
+
+    inner.pos.cmpge(outer.pos).all() && inner_end.cmple(outer_end).all()
+}
+
+fn configure_pipeline(
+    device: &Device,
+    surface_format: TextureFormat,
+    camera_bind_group_layout: &BindGroupLayout,
+) -> RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("debug overlay"),
+        bind_group_layouts: &[camera_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    let shader = device.create_shader_module(include_wgsl!("shaders/debug_rect.wgsl"));
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("debug overlay"),
+        layout: Some(&pipeline_layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[DebugRect::layout()],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: surface_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::debug_overlay::{DebugOverlay, HIGHLIGHT_COLOR};
+    use crate::LayoutBox;
+    use glam::vec2;
+
+    #[test]
+    fn outlines_stay_inside_the_box() {
+        let mut overlay = DebugOverlay::default();
+
+        overlay.outline(
+            LayoutBox {
+                pos: vec2(10.0, 20.0),
+                size: vec2(30.0, 3.0),
+            },
+            2.0,
+            HIGHLIGHT_COLOR,
+        );
+
+        assert_eq!(overlay.rects.len(), 4);
+
+        for rect in overlay.rects.iter() {
+            assert!(rect.pos[0] >= 10.0 && rect.pos[0] + rect.size[0] <= 40.0);
+            assert!(rect.pos[1] >= 20.0 && rect.pos[1] + rect.size[1] <= 23.0);
+        }
+
+        overlay.clear();
+        overlay.outline(
+            LayoutBox {
+                pos: vec2(10.0, 20.0),
+                size: vec2(0.0, 5.0),
+            },
+            2.0,
+            HIGHLIGHT_COLOR,
+        );
+
+        assert_eq!(overlay.rects.len(), 1);
+        assert_eq!(overlay.rects[0].pos, [7.0, 17.0]);
+    }
+}
@@ -0,0 +1,317 @@
+//! A line based JSON protocol for inspecting a running app from another process.
+//!
+//! Every line sent to the server is a [`DevtoolsRequest`], answered by one [`DevtoolsMessage`]
+//! line. After a `watch_events` request, the connection additionally receives an `event` message
+//! for every event dispatched in the app.
+
+use crate::inspector::LayerSnapshot;
+use crate::widgets::events::EventDispatch;
+use crate::QuirkyApp;
+use async_std::io::{BufReader, WriteExt};
+use async_std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use futures::future::{select, Either};
+use futures::{AsyncBufReadExt, Stream, StreamExt};
+use futures_signals::signal::Mutable;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum DevtoolsRequest {
+    /// Fetches a snapshot of the widget tree of every layer
+    Tree,
+    /// Outlines a widget, or removes the outline when `id` is absent
+    Highlight { id: Option<Uuid> },
+    /// Streams every dispatched event over this connection
+    WatchEvents,
+    /// Lists the registered props and their current values
+    Props,
+    /// Sets a registered prop
+    Poke {
+        name: String,
+        value: serde_json::Value,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DevtoolsMessage {
+    Ok,
+    Error {
+        message: String,
+    },
+    Tree {
+        layers: Vec<LayerSnapshot>,
+    },
+    Props {
+        props: BTreeMap<String, serde_json::Value>,
+    },
+    Event {
+        dispatch: EventDispatch,
+    },
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = EventDispatch> + Send>>;
+
+struct RegisteredProp {
+    get: Box<dyn Fn() -> anyhow::Result<serde_json::Value> + Send + Sync>,
+    set: Box<dyn Fn(serde_json::Value) -> anyhow::Result<()> + Send + Sync>,
+}
+
+/// Serves the devtools protocol for an app.
+pub struct DevtoolsServer {
+    app: Arc<QuirkyApp>,
+    props: Mutex<BTreeMap<String, RegisteredProp>>,
+}
+
+impl DevtoolsServer {
+    pub fn new(app: Arc<QuirkyApp>) -> Arc<Self> {
+        Arc::new(Self {
+            app,
+            props: Default::default(),
+        })
+    }
+
+    /// Makes `prop` readable and writable through the `props` and `poke` requests.
+    pub fn register_prop<T>(&self, name: impl Into<String>, prop: Mutable<T>)
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let getter = prop.clone();
+
+        self.props.lock().unwrap().insert(
+            name.into(),
+            RegisteredProp {
+                get: Box::new(move || Ok(serde_json::to_value(&*getter.lock_ref())?)),
+                set: Box::new(move |value| {
+                    prop.set(serde_json::from_value(value)?);
+                    Ok(())
+                }),
+            },
+        );
+    }
+
+    /// Accepts connections on `addr`, failing only when it cannot be bound.
+    ///
+    /// A connection that fails to be accepted is logged and skipped.
+    pub async fn listen(self: Arc<Self>, addr: impl ToSocketAddrs) -> anyhow::Result<()> {
+        self.serve(TcpListener::bind(addr).await?).await;
+
+        Ok(())
+    }
+
+    /// Accepts connections on an already bound listener, see [`DevtoolsServer::listen`]
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) {
+        let mut incoming = listener.incoming();
+
+        while let Some(stream) = incoming.next().await {
+            let server = self.clone();
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::warn!("devtools failed to accept a connection: {e}");
+                    continue;
+                }
+            };
+
+            async_std::task::spawn(async move {
+                let _ = server.handle_connection(stream).await;
+            });
+        }
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> anyhow::Result<()> {
+        let mut lines = BufReader::new(stream.clone()).lines();
+        let mut writer = stream;
+        let mut events: Option<EventStream> = None;
+
+        loop {
+            let incoming = match events.as_mut() {
+                Some(event_stream) => match select(lines.next(), event_stream.next()).await {
+                    Either::Left((line, _)) => Either::Left(line),
+                    Either::Right((dispatch, _)) => Either::Right(dispatch),
+                },
+                None => Either::Left(lines.next().await),
+            };
+
+            let message = match incoming {
+                Either::Left(Some(line)) => self.handle_request(&line?, &mut events),
+                Either::Left(None) => return Ok(()),
+                Either::Right(Some(dispatch)) => DevtoolsMessage::Event { dispatch },
+                Either::Right(None) => {
+                    events = None;
+                    continue;
+                }
+            };
+
+            let mut out = serde_json::to_string(&message)?;
+            out.push('\n');
+
+            writer.write_all(out.as_bytes()).await?;
+        }
+    }
+
+    fn handle_request(&self, line: &str, events: &mut Option<EventStream>) -> DevtoolsMessage {
+        let request = match serde_json::from_str::<DevtoolsRequest>(line) {
+            Ok(request) => request,
+            Err(e) => {
+                return DevtoolsMessage::Error {
+                    message: format!("invalid request: {e}"),
+                }
+            }
+        };
+
+        match self.execute(request, events) {
+            Ok(message) => message,
+            Err(e) => DevtoolsMessage::Error {
+                message: e.to_string(),
+            },
+        }
+    }
+
+    fn execute(
+        &self,
+        request: DevtoolsRequest,
+        events: &mut Option<EventStream>,
+    ) -> anyhow::Result<DevtoolsMessage> {
+        match request {
+            DevtoolsRequest::Tree => Ok(DevtoolsMessage::Tree {
+                layers: self.app.snapshot_tree(),
+            }),
+            DevtoolsRequest::Highlight { id } => {
+                self.app.highlighted_widget.set(id);
+                Ok(DevtoolsMessage::Ok)
+            }
+            DevtoolsRequest::WatchEvents => {
+                events.get_or_insert_with(|| Box::pin(self.app.context.tap_events()));
+                Ok(DevtoolsMessage::Ok)
+            }
+            DevtoolsRequest::Props => {
+                let props = self.props.lock().unwrap();
+
+                Ok(DevtoolsMessage::Props {
+                    props: props
+                        .iter()
+                        .map(|(name, prop)| Ok((name.clone(), (prop.get)()?)))
+                        .collect::<anyhow::Result<_>>()?,
+                })
+            }
+            DevtoolsRequest::Poke { name, value } => {
+                let props = self.props.lock().unwrap();
+                let prop = props
+                    .get(&name)
+                    .ok_or_else(|| anyhow::anyhow!("no prop named {name}"))?;
+
+                (prop.set)(value)?;
+
+                Ok(DevtoolsMessage::Ok)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::devtools::{DevtoolsMessage, DevtoolsRequest, DevtoolsServer};
+    use crate::drawable_tree::test::TestWidget;
+    use crate::headless::request_headless_device;
+    use crate::widget::{Widget, WidgetBase};
+    use crate::QuirkyApp;
+    use async_std::io::{BufReader, WriteExt};
+    use async_std::net::{TcpListener, TcpStream};
+    use futures::{AsyncBufReadExt, Stream, StreamExt};
+    use futures_signals::signal::Mutable;
+    use std::sync::Arc;
+    use wgpu::TextureFormat;
+
+    #[test]
+    fn requests_are_tagged_by_command() {
+        let request: DevtoolsRequest = serde_json::from_str(
+            r#"{"command": "poke", "name": "theme", "value": {"color": [1, 0, 0, 1]}}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(request, DevtoolsRequest::Poke { name, .. } if name == "theme"));
+
+        let request: DevtoolsRequest = serde_json::from_str(r#"{"command": "highlight"}"#).unwrap();
+
+        assert!(matches!(request, DevtoolsRequest::Highlight { id: None }));
+    }
+
+    async fn request(
+        stream: &mut TcpStream,
+        replies: &mut (impl Stream<Item = std::io::Result<String>> + Unpin),
+        line: &str,
+    ) -> DevtoolsMessage {
+        stream
+            .write_all(format!("{line}\n").as_bytes())
+            .await
+            .unwrap();
+
+        serde_json::from_str(&replies.next().await.unwrap().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a GPU adapter"]
+    async fn serves_requests_over_tcp() {
+        let (device, queue) = request_headless_device().await.unwrap();
+        let root: Arc<dyn Widget> = TestWidget::new(0.0, 0.0, vec![]);
+        let app = Arc::new(QuirkyApp::new(
+            device,
+            queue,
+            TextureFormat::Rgba8UnormSrgb,
+            |_, _, _| {},
+            {
+                let root = root.clone();
+                move |_| root
+            },
+        ));
+
+        let server = DevtoolsServer::new(app.clone());
+        let count = Mutable::new(1u32);
+        server.register_prop("count", count.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        async_std::task::spawn(server.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut replies = BufReader::new(stream.clone()).lines();
+
+        let reply = request(&mut stream, &mut replies, r#"{"command": "tree"}"#).await;
+        assert!(
+            matches!(reply, DevtoolsMessage::Tree { layers } if layers[0].tree.id == root.id())
+        );
+
+        let highlight = format!(r#"{{"command": "highlight", "id": "{}"}}"#, root.id());
+        let reply = request(&mut stream, &mut replies, &highlight).await;
+        assert!(matches!(reply, DevtoolsMessage::Ok));
+        assert_eq!(app.highlighted_widget.get(), Some(root.id()));
+
+        let reply = request(
+            &mut stream,
+            &mut replies,
+            r#"{"command": "poke", "name": "count", "value": 5}"#,
+        )
+        .await;
+        assert!(matches!(reply, DevtoolsMessage::Ok));
+        assert_eq!(count.get(), 5);
+
+        let reply = request(&mut stream, &mut replies, r#"{"command": "props"}"#).await;
+        assert!(
+            matches!(reply, DevtoolsMessage::Props { props } if props["count"] == serde_json::json!(5))
+        );
+
+        let reply = request(
+            &mut stream,
+            &mut replies,
+            r#"{"command": "poke", "name": "missing", "value": 5}"#,
+        )
+        .await;
+        assert!(matches!(reply, DevtoolsMessage::Error { .. }));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::opacity_groups::OpacityGroup;
+use crate::quirky_app_context::{QuirkyAppContext, QuirkyResources};
+use crate::widget::Widget;
+use crate::LayoutBox;
+use glam::{Affine2, IVec2, UVec2, Vec2};
+use image::RgbaImage;
+use std::sync::Arc;
+
+/// A backend independent description of what a primitive draws.
+///
+/// Colors are in linear space, exactly as the wgpu pipelines write them to an srgb target.
+#[derive(Clone, Debug)]
+pub enum DisplayItem {
+    Quad {
+        pos: Vec2,
+        size: Vec2,
+        color: [f32; 4],
+    },
+    /// An outline drawn on the inside of the box
+    Border {
+        pos: Vec2,
+        size: Vec2,
+        thickness: f32,
+        color: [f32; 4],
+    },
+    /// An image stretched to fill the box, replacing the pixels underneath it.
+    ///
+    /// Unless `srgb` is set, the pixels are linear, the way the textured quad pipeline samples them.
+    Image {
+        pos: Vec2,
+        size: Vec2,
+        image: Arc<RgbaImage>,
+        srgb: bool,
+    },
+    /// Already rasterized spans of srgb pixels, blended over the target and clipped to `clip`
+    Spans {
+        clip: LayoutBox,
+        spans: Vec<PixelSpan>,
+    },
+    /// Maps the logical space of the items that follow to the target, replacing the previous
+    /// transform, see [`crate::widget::WidgetBase::transform`]
+    Transform(Affine2),
+    /// Restricts the items that follow to a rect already transformed to the target, replacing the
+    /// previous clip, see [`crate::widget::Widget::clip_rect`]
+    Clip(Option<LayoutBox>),
+    /// Draws the items up to the matching [`DisplayItem::PopGroup`] into a layer of their own,
+    /// composited with the group's opacity and blend mode and clipped to `clip`, which is already
+    /// transformed to the target
+    PushGroup {
+        group: OpacityGroup,
+        clip: LayoutBox,
+    },
+    PopGroup,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PixelSpan {
+    pub pos: IVec2,
+    pub size: UVec2,
+    pub color: [u8; 4],
+}
+
+#[derive(Default, Debug)]
+pub struct DisplayList {
+    items: Vec<DisplayItem>,
+    transform: Affine2,
+    clip: Option<LayoutBox>,
+}
+
+impl DisplayList {
+    pub fn push(&mut self, item: DisplayItem) {
+        self.items.push(item);
+    }
+
+    /// Pushes a [`DisplayItem::Transform`], unless `transform` already applies
+    pub fn set_transform(&mut self, transform: Affine2) {
+        if self.transform != transform {
+            self.transform = transform;
+            self.items.push(DisplayItem::Transform(transform));
+        }
+    }
+
+    /// Pushes a [`DisplayItem::Clip`], unless `clip` already applies
+    pub fn set_clip(&mut self, clip: Option<LayoutBox>) {
+        if self.clip != clip {
+            self.clip = clip;
+            self.items.push(DisplayItem::Clip(clip));
+        }
+    }
+
+    pub fn items(&self) -> &[DisplayItem] {
+        &self.items
+    }
+}
+
+/// Encodes a linear color channel the way an srgb render target stores it
+pub fn linear_to_srgb(channel: f32) -> u8 {
+    let channel = channel.clamp(0.0, 1.0);
+
+    let encoded = if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    };
+
+    (encoded * 255.0).round() as u8
+}
+
+/// Prepares every widget in the tree and collects the description of their primitives, in draw
+/// order, along with the transforms, clip rects and opacity groups the widgets apply.
+///
+/// No graphics device is involved, and the widgets cached primitives and dirty flags are left
+/// untouched.
+pub fn collect_display_list(
+    widget: &Arc<dyn Widget>,
+    ctx: &QuirkyAppContext,
+    resources: &mut QuirkyResources,
+    display_list: &mut DisplayList,
+) {
+    collect_widget(
+        widget,
+        Affine2::IDENTITY,
+        None,
+        ctx,
+        resources,
+        display_list,
+    );
+}
+
+fn collect_widget(
+    widget: &Arc<dyn Widget>,
+    parent_transform: Affine2,
+    parent_clip: Option<LayoutBox>,
+    ctx: &QuirkyAppContext,
+    resources: &mut QuirkyResources,
+    display_list: &mut DisplayList,
+) {
+    let transform = match widget.transform().get() {
+        Some(local) => parent_transform * local,
+        None => parent_transform,
+    };
+    let clip = match (parent_clip, widget.clip_rect()) {
+        (Some(parent_clip), Some(clip_rect)) => Some(
+            parent_clip
+                .intersect(&clip_rect.transformed(&transform))
+                .unwrap_or_default(),
+        ),
+        (None, Some(clip_rect)) => Some(clip_rect.transformed(&transform)),
+        (parent_clip, None) => parent_clip,
+    };
+    let opacity_group = widget.opacity_group();
+
+    if let Some(group) = opacity_group {
+        display_list.push(DisplayItem::PushGroup {
+            group,
+            clip: widget.bounding_box().get().transformed(&transform),
+        });
+    }
+
+    widget.prepare_parallel(ctx, resources.shared());
+
+    let primitives = widget.prepare(ctx, resources);
+
+    if !primitives.is_empty() {
+        display_list.set_transform(transform);
+        display_list.set_clip(clip);
+    }
+
+    for primitive in primitives {
+        primitive.describe(resources, display_list);
+    }
+
+    if let Some(children) = widget.children() {
+        children
+            .iter()
+            .for_each(|child| collect_widget(child, transform, clip, ctx, resources, display_list));
+    }
+
+    if opacity_group.is_some() {
+        display_list.push(DisplayItem::PopGroup);
+    }
+}
@@ -1,6 +1,38 @@
+use crate::display_list::DisplayList;
+use crate::pipeline_registry::PipelineId;
+use crate::quirky_app_context::QuirkyResources;
 use crate::render_contexts::{PrepareContext, RenderContext};
+use wgpu::BufferSlice;
 
 pub trait DrawablePrimitive: Send + Sync {
     fn prepare(&mut self, _prepare_context: &mut PrepareContext) {}
     fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, render_context: &'a RenderContext<'a>);
+
+    /// The registered pipeline this primitive draws with, if it draws with a single known one,
+    /// see [`PrepareContext::pipeline`]
+    fn pipeline_id(&self) -> Option<PipelineId> {
+        None
+    }
+
+    /// Appends the instances of a batchable primitive to `out`, returning how many were written.
+    ///
+    /// Consecutive batchable primitives with the same [`DrawablePrimitive::pipeline_id`] are
+    /// merged by the renderer and drawn once, with [`DrawablePrimitive::draw_instanced`] of the
+    /// first one. Their own `draw` is not called.
+    fn write_instances(&self, _out: &mut Vec<u8>) -> Option<u32> {
+        None
+    }
+
+    /// Draws `instance_count` instances of a batch, read from `instances`
+    fn draw_instanced<'a>(
+        &'a self,
+        _pass: &mut wgpu::RenderPass<'a>,
+        _render_context: &'a RenderContext<'a>,
+        _instances: BufferSlice<'a>,
+        _instance_count: u32,
+    ) {
+    }
+
+    /// Describes the primitive for backends without a wgpu device, such as a software rasterizer
+    fn describe(&self, _resources: &mut QuirkyResources, _display_list: &mut DisplayList) {}
 }
@@ -0,0 +1,963 @@
+use crate::clip_stack::ClipStack;
+use crate::damage::DamageTracker;
+use crate::drawable_primitive::DrawablePrimitive;
+use crate::frame_stats::FrameStats;
+use crate::opacity_groups::OpacityGroup;
+use crate::quirky_app_context::{QuirkyAppContext, QuirkyResources};
+use crate::render_contexts::PrepareContext;
+use crate::widget::Widget;
+use crate::LayoutBox;
+use glam::{Affine2, UVec2};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// The widgets that changed since the last frame, as reported by the widgets themselves.
+///
+/// The [`DrawableTree`] hands these to every widget it draws through
+/// [`crate::widget::WidgetBase::set_invalidations`], and only revisits the reported widgets.
+#[derive(Clone, Default)]
+pub struct Invalidations {
+    widget_ids: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+impl Invalidations {
+    /// Has the next frame read the widget's box, transform, clip rect, opacity group and children
+    /// again, preparing it again if it is dirty
+    pub fn invalidate(&self, widget_id: Uuid) {
+        self.widget_ids.lock().unwrap().insert(widget_id);
+    }
+
+    fn take(&self) -> HashSet<Uuid> {
+        std::mem::take(&mut self.widget_ids.lock().unwrap())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.widget_ids.lock().unwrap().is_empty()
+    }
+}
+
+/// A widget as it was last drawn
+struct Node {
+    widget: Arc<dyn Widget>,
+    parent: Option<Uuid>,
+    depth: usize,
+    children: Vec<Uuid>,
+    primitives: Vec<Box<dyn DrawablePrimitive>>,
+    /// Whether the widget was queued to be prepared since joining the tree
+    prepared: bool,
+    /// Whether the primitives are queued to be prepared
+    needs_prepare: bool,
+    /// Maps the logical space of the widget to the screen
+    transform: Affine2,
+    /// The enclosing clip rect in logical units, already transformed to the screen
+    clip: Option<LayoutBox>,
+    opacity_group: Option<OpacityGroup>,
+    /// The innermost group widget enclosing the widget, itself included
+    group: Option<Uuid>,
+}
+
+/// The primitives of a widget, along with what its ancestors apply to them
+pub(crate) struct WidgetDrawables<'a> {
+    pub primitives: &'a [Box<dyn DrawablePrimitive>],
+    /// The enclosing clip rect in logical units, already transformed to the screen
+    pub clip: Option<LayoutBox>,
+    /// Maps the logical space of the widget to the screen
+    pub transform: Affine2,
+    /// The innermost opacity group the widget is drawn into, `None` for the frame itself
+    pub group: Option<usize>,
+}
+
+/// A widget subtree drawn into a texture of its own, see [`Widget::opacity_group`]
+pub(crate) struct GroupDrawables {
+    pub widget_id: Uuid,
+    pub group: OpacityGroup,
+    /// The group this one is composited into, `None` for the frame itself
+    pub parent: Option<usize>,
+    /// The index of the group widget's drawables, where the group is composited
+    pub first: usize,
+    /// The area the group is composited onto, in logical units
+    pub clip: LayoutBox,
+}
+
+/// Where a group sits in the drawing order
+struct GroupSlot {
+    widget_id: Uuid,
+    parent: Option<usize>,
+    first: usize,
+}
+
+/// What updating the tree needs from the frame being prepared
+pub(crate) struct TreeUpdate<'a> {
+    pub ctx: &'a QuirkyAppContext,
+    pub resources: &'a mut QuirkyResources,
+    pub damage: &'a mut DamageTracker,
+    pub stats: &'a mut FrameStats,
+}
+
+/// The widget trees as last drawn, along with their primitives, kept across frames.
+///
+/// Each frame only revisits the widgets reported through [`Invalidations`], and the descendants
+/// of those whose transform, clip or opacity group changed. The drawing order is only rebuilt
+/// when children are added, removed or reordered, so that a frame costs in proportion to what
+/// changed rather than to the size of the tree.
+#[derive(Default)]
+pub(crate) struct DrawableTree {
+    invalidations: Invalidations,
+    nodes: HashMap<Uuid, Node>,
+    roots: Vec<Uuid>,
+    /// The widgets in drawing order
+    order: Vec<Uuid>,
+    groups: Vec<GroupSlot>,
+    group_index: HashMap<Uuid, usize>,
+    structure_changed: bool,
+    /// Widgets that were dirty or new, to be prepared once the tree is patched
+    dirty: Vec<Uuid>,
+    /// Widgets whose primitives need preparing before the next draw
+    to_prepare: Vec<Uuid>,
+    /// Widgets dropped by their parent, along with that parent, removed once every invalidated
+    /// widget was visited as they may have been moved to another parent
+    removed: Vec<(Uuid, Option<Uuid>)>,
+    primitive_count: usize,
+    /// The target the primitives were last prepared for
+    target: Option<(UVec2, f64)>,
+}
+
+impl DrawableTree {
+    /// Patches the tree to match `roots`, preparing the widgets that became dirty
+    pub fn update(&mut self, roots: &[Arc<dyn Widget>], update: &mut TreeUpdate) {
+        let invalidated = self.invalidations.take();
+        let root_ids: Vec<Uuid> = roots.iter().map(|root| root.id()).collect();
+
+        if root_ids != self.roots {
+            for id in self.roots.iter() {
+                if !root_ids.contains(id) {
+                    self.removed.push((*id, None));
+                }
+            }
+
+            for root in roots {
+                match self.nodes.get_mut(&root.id()) {
+                    Some(node) if node.parent.is_none() => {}
+                    // Moved over from a parent, which will not remove it anymore
+                    Some(node) => {
+                        node.parent = None;
+                        node.depth = 0;
+                        self.visit(root.id(), update);
+                    }
+                    None => {
+                        self.insert(root.clone(), None, 0);
+                        self.visit(root.id(), update);
+                    }
+                }
+            }
+
+            self.roots = root_ids;
+            self.structure_changed = true;
+        }
+
+        // Ancestors first, as visiting them may revisit or remove their descendants
+        let mut invalidated: Vec<(usize, Uuid)> = invalidated
+            .into_iter()
+            .filter_map(|id| self.nodes.get(&id).map(|node| (node.depth, id)))
+            .collect();
+        invalidated.sort_unstable_by_key(|(depth, _)| *depth);
+
+        for (_, id) in invalidated {
+            if self.nodes.contains_key(&id) {
+                self.visit(id, update);
+            }
+        }
+
+        for (id, parent) in std::mem::take(&mut self.removed) {
+            self.remove_subtree(id, parent, update.damage);
+        }
+
+        self.prepare_widgets(update);
+
+        if self.structure_changed {
+            let _span = tracing::debug_span!("flatten_drawable_tree").entered();
+
+            self.flatten();
+            self.structure_changed = false;
+        }
+
+        update.stats.widgets = self.nodes.len();
+        update.stats.primitives = self.primitive_count;
+    }
+
+    /// Whether widgets changed since the last update
+    pub(crate) fn has_invalidations(&self) -> bool {
+        !self.invalidations.is_empty()
+    }
+
+    /// Has every primitive prepared again when the target they draw into changed size or scale
+    pub fn set_target(&mut self, screen_resolution: UVec2, scale_factor: f64) {
+        let target = Some((screen_resolution, scale_factor));
+
+        if self.target != target {
+            self.target = target;
+
+            for (id, node) in self.nodes.iter_mut() {
+                if !node.needs_prepare {
+                    node.needs_prepare = true;
+                    self.to_prepare.push(*id);
+                }
+            }
+        }
+    }
+
+    /// Prepares the primitives of the widgets that were prepared or moved since the last call,
+    /// returning how many were prepared
+    pub fn prepare_primitives(&mut self, paint_ctx: &mut PrepareContext) -> usize {
+        let mut prepared = 0;
+
+        for id in self.to_prepare.drain(..) {
+            // The widget may have been removed since
+            let Some(node) = self.nodes.get_mut(&id) else {
+                continue;
+            };
+
+            node.needs_prepare = false;
+            paint_ctx.widget_id = id;
+            paint_ctx.transform = node.transform;
+
+            for (index, primitive) in node.primitives.iter_mut().enumerate() {
+                let _span = tracing::trace_span!("primitive_prepare").entered();
+                paint_ctx.primitive_index = index;
+                primitive.prepare(paint_ctx);
+            }
+
+            prepared += node.primitives.len();
+        }
+
+        prepared
+    }
+
+    /// The drawables of every widget in drawing order
+    pub fn drawables(&self) -> impl Iterator<Item = WidgetDrawables<'_>> {
+        self.order.iter().map(|id| {
+            let node = &self.nodes[id];
+
+            WidgetDrawables {
+                primitives: &node.primitives,
+                clip: node.clip,
+                transform: node.transform,
+                group: node.group.map(|group| self.group_index[&group]),
+            }
+        })
+    }
+
+    /// The opacity groups, indexed by [`WidgetDrawables::group`], parents before their children
+    pub fn groups(&self) -> Vec<GroupDrawables> {
+        self.groups
+            .iter()
+            .map(|slot| {
+                let node = &self.nodes[&slot.widget_id];
+
+                GroupDrawables {
+                    widget_id: slot.widget_id,
+                    group: node
+                        .opacity_group
+                        .expect("group widgets are flattened again when their group is removed"),
+                    parent: slot.parent,
+                    first: slot.first,
+                    clip: node.clip.unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    fn insert(&mut self, widget: Arc<dyn Widget>, parent: Option<Uuid>, depth: usize) {
+        widget.set_invalidations(self.invalidations.clone());
+
+        self.nodes.insert(
+            widget.id(),
+            Node {
+                widget,
+                parent,
+                depth,
+                children: vec![],
+                primitives: vec![],
+                prepared: false,
+                needs_prepare: false,
+                transform: Affine2::IDENTITY,
+                clip: None,
+                opacity_group: None,
+                group: None,
+            },
+        );
+    }
+
+    /// Reads the widget again, queueing it to be prepared if it is dirty or new, and revisits the
+    /// children that are new or inherit something that changed
+    fn visit(&mut self, id: Uuid, update: &mut TreeUpdate) {
+        update.stats.visited_widgets += 1;
+
+        let node = &self.nodes[&id];
+        let widget = node.widget.clone();
+        let (parent_transform, parent_clip, parent_group) =
+            match node.parent.and_then(|parent| self.nodes.get(&parent)) {
+                Some(parent) => (parent.transform, parent.clip, parent.group),
+                None => (Affine2::IDENTITY, None, None),
+            };
+
+        let transform = match widget.transform().get() {
+            Some(local) => parent_transform * local,
+            None => parent_transform,
+        };
+        let bb = widget.bounding_box().get().transformed(&transform);
+        let dirty = widget.dirty().get() || !node.prepared;
+
+        update.damage.visit(id, bb, dirty);
+
+        let mut clips = ClipStack::default();
+
+        if let Some(clip) = parent_clip {
+            clips.push(clip);
+        }
+
+        if let Some(clip_rect) = widget.clip_rect() {
+            clips.push(clip_rect.transformed(&transform));
+        }
+
+        let opacity_group = widget.opacity_group();
+
+        if opacity_group.is_some() {
+            clips.push(bb);
+        }
+
+        let clip = clips.current();
+        let group = opacity_group.map(|_| id).or(parent_group);
+
+        if dirty {
+            widget.clear_dirty();
+            update.stats.dirty_widgets += 1;
+            self.dirty.push(id);
+        }
+
+        let node = self.nodes.get_mut(&id).unwrap();
+        let inherited_changed =
+            node.transform != transform || node.clip != clip || node.group != group;
+
+        if node.opacity_group.is_some() != opacity_group.is_some() {
+            self.structure_changed = true;
+        }
+
+        // Primitives drawn outside of the camera bake the transform in when preparing
+        if node.transform != transform && !node.needs_prepare {
+            node.needs_prepare = true;
+            self.to_prepare.push(id);
+        }
+
+        node.prepared |= dirty;
+
+        node.transform = transform;
+        node.clip = clip;
+        node.opacity_group = opacity_group;
+        node.group = group;
+
+        let children = widget.children().unwrap_or_default();
+        let child_ids: Vec<Uuid> = children.iter().map(|child| child.id()).collect();
+        let previous_children = std::mem::replace(&mut node.children, child_ids.clone());
+        let depth = node.depth;
+
+        if previous_children != child_ids {
+            self.structure_changed = true;
+
+            let current: HashSet<Uuid> = child_ids.into_iter().collect();
+
+            for child_id in previous_children {
+                if !current.contains(&child_id) {
+                    self.removed.push((child_id, Some(id)));
+                }
+            }
+        }
+
+        for child in children {
+            let child_id = child.id();
+
+            match self.nodes.get_mut(&child_id) {
+                Some(child_node) if child_node.parent == Some(id) => {
+                    child_node.depth = depth + 1;
+
+                    if inherited_changed {
+                        self.visit(child_id, update);
+                    }
+                }
+                // Moved over from another parent, which will not remove it anymore
+                Some(child_node) => {
+                    child_node.parent = Some(id);
+                    child_node.depth = depth + 1;
+                    self.visit(child_id, update);
+                }
+                None => {
+                    self.insert(child, Some(id), depth + 1);
+                    self.visit(child_id, update);
+                }
+            }
+        }
+    }
+
+    /// Prepares the widgets queued by [`DrawableTree::visit`], running
+    /// [`Widget::prepare_parallel`] for all of them on the rayon thread pool before preparing
+    /// them one after the other
+    fn prepare_widgets(&mut self, update: &mut TreeUpdate) {
+        if self.dirty.is_empty() {
+            return;
+        }
+
+        // A widget may have been removed after being queued
+        let widgets: Vec<(Uuid, Arc<dyn Widget>)> = self
+            .dirty
+            .drain(..)
+            .filter_map(|id| self.nodes.get(&id).map(|node| (id, node.widget.clone())))
+            .collect();
+
+        {
+            let _span = tracing::debug_span!("widget_prepare_parallel").entered();
+
+            let ctx = update.ctx;
+            let shared = update.resources.shared();
+
+            widgets.par_iter().for_each(|(_, widget)| {
+                let _span =
+                    tracing::trace_span!("widget_prepare_parallel", widget = widget.type_name())
+                        .entered();
+
+                widget.prepare_parallel(ctx, shared);
+            });
+        }
+
+        for (id, widget) in widgets {
+            let _span =
+                tracing::trace_span!("widget_prepare", widget = widget.type_name()).entered();
+
+            let primitives = widget.prepare(update.ctx, update.resources);
+            let node = self.nodes.get_mut(&id).unwrap();
+
+            self.primitive_count -= node.primitives.len();
+            self.primitive_count += primitives.len();
+            widget.set_cached_primitive_count(primitives.len());
+            node.primitives = primitives;
+
+            if !node.needs_prepare {
+                node.needs_prepare = true;
+                self.to_prepare.push(id);
+            }
+        }
+    }
+
+    /// Removes a widget and its descendants, unless it was moved to another parent meanwhile
+    fn remove_subtree(&mut self, id: Uuid, parent: Option<Uuid>, damage: &mut DamageTracker) {
+        if !matches!(self.nodes.get(&id), Some(node) if node.parent == parent) {
+            return;
+        }
+
+        let node = self.nodes.remove(&id).unwrap();
+
+        damage.remove(id);
+        self.primitive_count -= node.primitives.len();
+
+        for child in node.children {
+            self.remove_subtree(child, Some(id), damage);
+        }
+    }
+
+    /// Rebuilds the drawing order and the groups from the structure of the tree
+    fn flatten(&mut self) {
+        self.order.clear();
+        self.groups.clear();
+        self.group_index.clear();
+
+        for root in self.roots.clone() {
+            self.flatten_node(root, None);
+        }
+    }
+
+    fn flatten_node(&mut self, id: Uuid, parent_group: Option<usize>) {
+        let node = &self.nodes[&id];
+        let mut group = parent_group;
+
+        if node.opacity_group.is_some() {
+            group = Some(self.groups.len());
+
+            self.group_index.insert(id, self.groups.len());
+            self.groups.push(GroupSlot {
+                widget_id: id,
+                parent: parent_group,
+                first: self.order.len(),
+            });
+        }
+
+        self.order.push(id);
+
+        for child in node.children.clone() {
+            self.flatten_node(child, group);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::damage::DamageTracker;
+    use crate::drawable_primitive::DrawablePrimitive;
+    use crate::drawable_tree::{DrawableTree, Invalidations, TreeUpdate};
+    use crate::frame_stats::FrameStats;
+    use crate::opacity_groups::{BlendMode, OpacityGroup};
+    use crate::quirky_app_context::{QuirkyAppContext, QuirkyResources, SharedResources};
+    use crate::widget::{hit_test, Widget, WidgetBase};
+    use crate::LayoutBox;
+    use futures::future::BoxFuture;
+    use futures::stream::FuturesUnordered;
+    use futures_signals::signal::{Mutable, ReadOnlyMutable};
+    use glam::{vec2, Affine2, UVec2, Vec2};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    pub(crate) struct TestWidget {
+        id: Uuid,
+        bounding_box: Mutable<LayoutBox>,
+        transform: Mutable<Option<Affine2>>,
+        dirty: Mutable<bool>,
+        cached_primitive_count: Mutable<usize>,
+        invalidations: Mutex<Option<Invalidations>>,
+        children: Mutex<Vec<Arc<dyn Widget>>>,
+        opacity_group: Mutex<Option<OpacityGroup>>,
+        prepare_count: AtomicUsize,
+        prepare_parallel_count: AtomicUsize,
+        /// The [`TICKS`] of the last `prepare_parallel` and `prepare` calls
+        prepared_at: Mutex<(usize, usize)>,
+    }
+
+    /// A clock shared by all test widgets, for ordering calls across widgets
+    static TICKS: AtomicUsize = AtomicUsize::new(0);
+
+    impl TestWidget {
+        pub(crate) fn new(x: f32, y: f32, children: Vec<Arc<dyn Widget>>) -> Arc<Self> {
+            Arc::new(Self {
+                id: Uuid::new_v4(),
+                bounding_box: Mutable::new(bb(x, y)),
+                transform: Mutable::new(None),
+                dirty: Mutable::new(true),
+                cached_primitive_count: Mutable::new(0),
+                invalidations: Mutex::new(None),
+                children: Mutex::new(children),
+                opacity_group: Mutex::new(None),
+                prepare_count: AtomicUsize::new(0),
+                prepare_parallel_count: AtomicUsize::new(0),
+                prepared_at: Mutex::new((0, 0)),
+            })
+        }
+
+        fn set_children(&self, children: Vec<Arc<dyn Widget>>) {
+            *self.children.lock().unwrap() = children;
+            self.invalidate();
+        }
+
+        fn set_opacity_group(&self, opacity_group: Option<OpacityGroup>) {
+            *self.opacity_group.lock().unwrap() = opacity_group;
+            self.invalidate();
+        }
+
+        fn prepare_count(&self) -> usize {
+            self.prepare_count.load(Ordering::SeqCst)
+        }
+
+        fn prepare_parallel_count(&self) -> usize {
+            self.prepare_parallel_count.load(Ordering::SeqCst)
+        }
+
+        fn prepared_at(&self) -> (usize, usize) {
+            *self.prepared_at.lock().unwrap()
+        }
+    }
+
+    impl WidgetBase for TestWidget {
+        fn id(&self) -> Uuid {
+            self.id
+        }
+
+        fn type_name(&self) -> &'static str {
+            "TestWidget"
+        }
+
+        fn bounding_box(&self) -> ReadOnlyMutable<LayoutBox> {
+            self.bounding_box.read_only()
+        }
+
+        fn set_bounding_box(&self, new_box: LayoutBox) {
+            if self.bounding_box.replace(new_box) != new_box {
+                self.invalidate();
+            }
+        }
+
+        fn transform(&self) -> ReadOnlyMutable<Option<Affine2>> {
+            self.transform.read_only()
+        }
+
+        fn set_transform(&self, transform: Option<Affine2>) {
+            if self.transform.replace(transform) != transform {
+                self.set_dirty();
+            }
+        }
+
+        fn dirty(&self) -> ReadOnlyMutable<bool> {
+            self.dirty.read_only()
+        }
+
+        fn set_dirty(&self) {
+            self.dirty.set(true);
+            self.invalidate();
+        }
+
+        fn clear_dirty(&self) {
+            self.dirty.set(false);
+        }
+
+        fn cached_primitive_count(&self) -> usize {
+            self.cached_primitive_count.get()
+        }
+
+        fn set_cached_primitive_count(&self, count: usize) {
+            self.cached_primitive_count.set(count);
+        }
+
+        fn set_invalidations(&self, invalidations: Invalidations) {
+            *self.invalidations.lock().unwrap() = Some(invalidations);
+        }
+
+        fn invalidate(&self) {
+            if let Some(invalidations) = self.invalidations.lock().unwrap().as_ref() {
+                invalidations.invalidate(self.id);
+            }
+        }
+
+        fn poll_prop_futures<'a>(
+            &'a self,
+            _ctx: &'a QuirkyAppContext,
+        ) -> FuturesUnordered<BoxFuture<'a, ()>> {
+            FuturesUnordered::new()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Widget for TestWidget {
+        fn children(&self) -> Option<Vec<Arc<dyn Widget>>> {
+            Some(self.children.lock().unwrap().clone())
+        }
+
+        fn prepare_parallel(
+            &self,
+            _quirky_context: &QuirkyAppContext,
+            _resources: &SharedResources,
+        ) {
+            self.prepare_parallel_count.fetch_add(1, Ordering::SeqCst);
+            self.prepared_at.lock().unwrap().0 = TICKS.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn prepare(
+            &self,
+            _quirky_context: &QuirkyAppContext,
+            _resources: &mut QuirkyResources,
+        ) -> Vec<Box<dyn DrawablePrimitive>> {
+            self.prepare_count.fetch_add(1, Ordering::SeqCst);
+            self.prepared_at.lock().unwrap().1 = TICKS.fetch_add(1, Ordering::SeqCst);
+
+            vec![]
+        }
+
+        fn opacity_group(&self) -> Option<OpacityGroup> {
+            *self.opacity_group.lock().unwrap()
+        }
+
+        fn get_widget_at(&self, pos: Vec2, mut path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+            let children = self.children.lock().unwrap().clone();
+
+            if let Some(hit) = children
+                .iter()
+                .rev()
+                .find_map(|child| hit_test(child, pos, path.clone()))
+            {
+                return Some(hit);
+            }
+
+            self.bounding_box.get().contains(pos).then(|| {
+                path.push(self.id);
+                path
+            })
+        }
+
+        async fn run(self: Arc<Self>, _ctx: &QuirkyAppContext) {}
+    }
+
+    fn bb(x: f32, y: f32) -> LayoutBox {
+        LayoutBox {
+            pos: vec2(x, y),
+            size: vec2(10.0, 10.0),
+        }
+    }
+
+    fn group() -> Option<OpacityGroup> {
+        Some(OpacityGroup {
+            opacity: 0.5,
+            blend_mode: BlendMode::Normal,
+        })
+    }
+
+    struct Frames {
+        ctx: QuirkyAppContext,
+        resources: QuirkyResources,
+        damage: DamageTracker,
+        tree: DrawableTree,
+    }
+
+    impl Frames {
+        fn new() -> Self {
+            let (tx, _rx) = async_std::channel::unbounded();
+
+            Self {
+                ctx: QuirkyAppContext::new(
+                    Mutable::new(UVec2::new(100, 100)).read_only(),
+                    tx,
+                    Default::default(),
+                ),
+                resources: Default::default(),
+                damage: Default::default(),
+                tree: Default::default(),
+            }
+        }
+
+        /// Updates the tree, returning the stats and damage of the frame
+        fn update(&mut self, roots: &[Arc<dyn Widget>]) -> (FrameStats, Option<LayoutBox>) {
+            let mut stats = FrameStats::default();
+
+            self.tree.update(
+                roots,
+                &mut TreeUpdate {
+                    ctx: &self.ctx,
+                    resources: &mut self.resources,
+                    damage: &mut self.damage,
+                    stats: &mut stats,
+                },
+            );
+
+            (stats, self.damage.finish())
+        }
+
+        fn groups_of_drawables(&self) -> Vec<Option<usize>> {
+            self.tree
+                .drawables()
+                .map(|drawables| drawables.group)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn only_invalidated_widgets_are_visited() {
+        let (a, b) = (
+            TestWidget::new(0.0, 0.0, vec![]),
+            TestWidget::new(20.0, 0.0, vec![]),
+        );
+        let root: Arc<dyn Widget> = TestWidget::new(0.0, 0.0, vec![a.clone(), b.clone()]);
+        let mut frames = Frames::new();
+
+        let (stats, _) = frames.update(&[root.clone()]);
+        assert_eq!(stats.visited_widgets, 3);
+        assert_eq!(stats.widgets, 3);
+
+        let (stats, damage) = frames.update(&[root.clone()]);
+        assert_eq!(stats.visited_widgets, 0);
+        assert_eq!(damage, None);
+
+        b.set_dirty();
+
+        let (stats, damage) = frames.update(&[root.clone()]);
+        assert_eq!(stats.visited_widgets, 1);
+        assert_eq!(stats.dirty_widgets, 1);
+        assert_eq!(damage, Some(bb(20.0, 0.0)));
+        assert_eq!((a.prepare_count(), b.prepare_count()), (1, 2));
+    }
+
+    #[test]
+    fn moved_children_are_kept() {
+        let child = TestWidget::new(30.0, 0.0, vec![]);
+        let from = TestWidget::new(0.0, 0.0, vec![child.clone()]);
+        let to = TestWidget::new(20.0, 0.0, vec![]);
+        let roots: Vec<Arc<dyn Widget>> = vec![from.clone(), to.clone()];
+        let mut frames = Frames::new();
+
+        frames.update(&roots);
+
+        // Whichever parent is visited first, the child is not removed
+        from.set_children(vec![]);
+        to.set_children(vec![child.clone()]);
+
+        let (stats, _) = frames.update(&roots);
+        assert_eq!(stats.widgets, 3);
+        assert_eq!(child.prepare_count(), 1);
+        assert_eq!(frames.tree.nodes[&child.id()].parent, Some(to.id()));
+        assert_eq!(frames.tree.order, vec![from.id(), to.id(), child.id()]);
+    }
+
+    #[test]
+    fn removed_subtrees_damage_their_old_boxes() {
+        let leaf = TestWidget::new(50.0, 50.0, vec![]);
+        let child = TestWidget::new(30.0, 0.0, vec![leaf.clone()]);
+        let root = TestWidget::new(0.0, 0.0, vec![child.clone()]);
+        let roots: Vec<Arc<dyn Widget>> = vec![root.clone()];
+        let mut frames = Frames::new();
+
+        frames.update(&roots);
+        root.set_children(vec![]);
+
+        let (stats, damage) = frames.update(&roots);
+        assert_eq!(stats.widgets, 1);
+        assert_eq!(
+            damage,
+            Some(LayoutBox {
+                pos: vec2(30.0, 0.0),
+                size: vec2(30.0, 60.0),
+            })
+        );
+        assert!(!frames.tree.nodes.contains_key(&leaf.id()));
+    }
+
+    #[test]
+    fn groups_follow_their_widgets() {
+        let leaf = TestWidget::new(0.0, 0.0, vec![]);
+        let grouped = TestWidget::new(0.0, 0.0, vec![leaf.clone()]);
+        let root = TestWidget::new(0.0, 0.0, vec![grouped.clone()]);
+        let roots: Vec<Arc<dyn Widget>> = vec![root.clone()];
+        let mut frames = Frames::new();
+
+        grouped.set_opacity_group(group());
+        frames.update(&roots);
+
+        let groups = frames.tree.groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].widget_id, grouped.id());
+        assert_eq!((groups[0].parent, groups[0].first), (None, 1));
+        assert_eq!(frames.groups_of_drawables(), vec![None, Some(0), Some(0)]);
+
+        grouped.set_opacity_group(None);
+        frames.update(&roots);
+
+        assert!(frames.tree.groups().is_empty());
+        assert_eq!(frames.groups_of_drawables(), vec![None, None, None]);
+    }
+
+    #[test]
+    fn nested_groups_composite_into_their_parent_group() {
+        let inner_leaf = TestWidget::new(5.0, 5.0, vec![]);
+        let inner = TestWidget::new(5.0, 5.0, vec![inner_leaf.clone()]);
+        let outer_leaf = TestWidget::new(0.0, 0.0, vec![]);
+        let outer = TestWidget::new(0.0, 0.0, vec![outer_leaf.clone(), inner.clone()]);
+        let sibling = TestWidget::new(20.0, 0.0, vec![]);
+        let root = TestWidget::new(0.0, 0.0, vec![outer.clone(), sibling.clone()]);
+        let roots: Vec<Arc<dyn Widget>> = vec![root.clone()];
+        let mut frames = Frames::new();
+
+        outer.set_opacity_group(group());
+        inner.set_opacity_group(group());
+        frames.update(&roots);
+
+        let groups = frames.tree.groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].widget_id, outer.id());
+        assert_eq!((groups[0].parent, groups[0].first), (None, 1));
+        assert_eq!(groups[0].clip, bb(0.0, 0.0));
+        assert_eq!(groups[1].widget_id, inner.id());
+        assert_eq!((groups[1].parent, groups[1].first), (Some(0), 3));
+        // Clipped to the outer group it is composited into
+        assert_eq!(
+            groups[1].clip,
+            LayoutBox {
+                pos: vec2(5.0, 5.0),
+                size: vec2(5.0, 5.0),
+            }
+        );
+        assert_eq!(
+            frames.groups_of_drawables(),
+            vec![None, Some(0), Some(0), Some(1), Some(1), None]
+        );
+
+        // Dropping the outer group composites the inner one into the frame
+        outer.set_opacity_group(None);
+        frames.update(&roots);
+
+        let groups = frames.tree.groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].widget_id, inner.id());
+        assert_eq!((groups[0].parent, groups[0].first), (None, 3));
+        assert_eq!(groups[0].clip, bb(5.0, 5.0));
+        assert_eq!(
+            frames.groups_of_drawables(),
+            vec![None, None, None, Some(0), Some(0), None]
+        );
+    }
+
+    #[test]
+    fn every_dirty_widget_is_prepared_in_parallel_before_any_is_prepared() {
+        let children: Vec<Arc<TestWidget>> = (0..4)
+            .map(|i| TestWidget::new(i as f32 * 20.0, 0.0, vec![]))
+            .collect();
+        let root = TestWidget::new(
+            0.0,
+            0.0,
+            children
+                .iter()
+                .map(|c| c.clone() as Arc<dyn Widget>)
+                .collect(),
+        );
+        let roots: [Arc<dyn Widget>; 1] = [root.clone()];
+        let mut frames = Frames::new();
+
+        frames.update(&roots);
+
+        for child in children.iter().skip(1) {
+            child.set_dirty();
+        }
+
+        frames.update(&roots);
+
+        let dirty = &children[1..];
+        let last_parallel = dirty.iter().map(|c| c.prepared_at().0).max().unwrap();
+        let first_prepare = dirty.iter().map(|c| c.prepared_at().1).min().unwrap();
+
+        assert!(last_parallel < first_prepare);
+        assert!(dirty
+            .iter()
+            .all(|c| (c.prepare_parallel_count(), c.prepare_count()) == (2, 2)));
+        assert_eq!(
+            (
+                children[0].prepare_parallel_count(),
+                children[0].prepare_count()
+            ),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn removed_widgets_are_not_prepared() {
+        let child = TestWidget::new(20.0, 0.0, vec![]);
+        let root = TestWidget::new(0.0, 0.0, vec![child.clone()]);
+        let roots: [Arc<dyn Widget>; 1] = [root.clone()];
+        let mut frames = Frames::new();
+
+        frames.update(&roots);
+
+        child.set_dirty();
+        root.set_children(vec![]);
+
+        let (stats, _) = frames.update(&roots);
+
+        assert_eq!(stats.widgets, 1);
+        assert_eq!(
+            (child.prepare_parallel_count(), child.prepare_count()),
+            (1, 1)
+        );
+    }
+}
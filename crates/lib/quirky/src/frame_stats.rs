@@ -0,0 +1,37 @@
+use crate::LayoutBox;
+use std::time::Duration;
+
+/// Statistics about the last frame drawn by [`crate::QuirkyApp::draw`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Number of frames drawn so far, including this one
+    pub frame: u64,
+    /// Widgets in the drawn trees
+    pub widgets: usize,
+    /// Widgets read again this frame, because they or an ancestor changed
+    pub visited_widgets: usize,
+    /// Widgets that created new primitives this frame
+    pub dirty_widgets: usize,
+    pub primitives: usize,
+    /// Primitives prepared this frame, as they were new or their widget moved
+    pub prepared_primitives: usize,
+    /// The area redrawn this frame in physical pixels, `None` when nothing changed
+    pub damage: Option<LayoutBox>,
+    /// Pipeline changes between consecutive draws, a batch counting as one draw.
+    ///
+    /// Primitives without a [`crate::drawable_primitive::DrawablePrimitive::pipeline_id`] always
+    /// count as a change.
+    pub pipeline_switches: usize,
+    /// Draw calls merging the instances of consecutive primitives
+    pub batches: usize,
+    /// Opacity groups redrawn into their textures
+    pub opacity_groups: usize,
+    /// Patching the drawable tree and preparing the dirty widgets
+    pub widget_prepare_time: Duration,
+    /// Preparing the primitives, including their gpu uploads
+    pub primitive_prepare_time: Duration,
+    /// Recording the draw calls of the render pass
+    pub encode_time: Duration,
+    /// The whole frame, including submission
+    pub total_time: Duration,
+}
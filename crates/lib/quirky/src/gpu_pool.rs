@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use wgpu::{Buffer, BufferUsages, Device, Extent3d, Texture, TextureFormat, TextureUsages};
+
+/// Identifies an allocation across re-prepares: the widget owning the primitive, the position of
+/// the primitive among the widget's primitives, and a name for the allocation within it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub widget_id: Uuid,
+    pub primitive_index: usize,
+    pub slot: &'static str,
+}
+
+struct PooledBuffer {
+    buffer: Arc<Buffer>,
+    usage: BufferUsages,
+    last_used: u64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct TextureSpec {
+    size: Extent3d,
+    format: TextureFormat,
+    usage: TextureUsages,
+}
+
+struct PooledTexture {
+    texture: Arc<Texture>,
+    spec: TextureSpec,
+    last_used: u64,
+}
+
+/// GPU allocations kept across frames, so that primitives recreated by a re-prepared widget
+/// update the buffers and textures of their predecessors instead of allocating new ones.
+///
+/// Allocations no primitive holds anymore and not requested for [`GpuPool::MAX_UNUSED_FRAMES`]
+/// frames, such as those of removed widgets, are dropped by [`GpuPool::end_frame`]. Primitives
+/// kept from earlier frames are not prepared again, so holding an allocation keeps it pooled.
+#[derive(Default)]
+pub struct GpuPool {
+    buffers: HashMap<PoolKey, PooledBuffer>,
+    textures: HashMap<PoolKey, PooledTexture>,
+    frame: u64,
+}
+
+impl GpuPool {
+    pub const MAX_UNUSED_FRAMES: u64 = 2;
+
+    /// A buffer of at least `size` bytes, reused when the one pooled under `key` is large enough.
+    ///
+    /// The contents of a reused buffer are left as they were, callers are expected to write
+    /// what they need with `queue.write_buffer`, so `usage` should include `COPY_DST`.
+    pub fn buffer(
+        &mut self,
+        device: &Device,
+        key: PoolKey,
+        label: &'static str,
+        size: u64,
+        usage: BufferUsages,
+    ) -> Arc<Buffer> {
+        let frame = self.frame;
+
+        if let Some(pooled) = self.buffers.get_mut(&key) {
+            if pooled.usage == usage && pooled.buffer.size() >= size {
+                pooled.last_used = frame;
+                return pooled.buffer.clone();
+            }
+        }
+
+        let buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size.max(wgpu::COPY_BUFFER_ALIGNMENT),
+            usage,
+            mapped_at_creation: false,
+        }));
+
+        self.buffers.insert(
+            key,
+            PooledBuffer {
+                buffer: buffer.clone(),
+                usage,
+                last_used: frame,
+            },
+        );
+
+        buffer
+    }
+
+    /// A 2d texture, reused when the one pooled under `key` has the same size, format and usage.
+    pub fn texture(
+        &mut self,
+        device: &Device,
+        key: PoolKey,
+        label: &'static str,
+        size: Extent3d,
+        format: TextureFormat,
+        usage: TextureUsages,
+    ) -> Arc<Texture> {
+        let frame = self.frame;
+        let spec = TextureSpec {
+            size,
+            format,
+            usage,
+        };
+
+        if let Some(pooled) = self.textures.get_mut(&key) {
+            if pooled.spec == spec {
+                pooled.last_used = frame;
+                return pooled.texture.clone();
+            }
+        }
+
+        let texture = Arc::new(device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        }));
+
+        self.textures.insert(
+            key,
+            PooledTexture {
+                texture: texture.clone(),
+                spec,
+                last_used: frame,
+            },
+        );
+
+        texture
+    }
+
+    /// Drops the allocations that went unused for too long, and starts the next frame.
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        let is_alive = |last_used: u64| frame - last_used < Self::MAX_UNUSED_FRAMES;
+
+        self.buffers.retain(|_, pooled| {
+            Arc::strong_count(&pooled.buffer) > 1 || is_alive(pooled.last_used)
+        });
+        self.textures.retain(|_, pooled| {
+            Arc::strong_count(&pooled.texture) > 1 || is_alive(pooled.last_used)
+        });
+
+        self.frame += 1;
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gpu_pool::{GpuPool, PoolKey};
+    use crate::headless::request_headless_device;
+    use std::sync::Arc;
+    use uuid::Uuid;
+    use wgpu::{BufferUsages, Extent3d, TextureFormat, TextureUsages};
+
+    const USAGE: BufferUsages = BufferUsages::VERTEX.union(BufferUsages::COPY_DST);
+
+    fn key(slot: &'static str) -> PoolKey {
+        PoolKey {
+            widget_id: Uuid::new_v4(),
+            primitive_index: 0,
+            slot,
+        }
+    }
+
+    fn extent(width: u32) -> Extent3d {
+        Extent3d {
+            width,
+            height: 4,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a GPU adapter"]
+    async fn buffers_are_reused_when_large_enough() {
+        let (device, _queue) = request_headless_device().await.unwrap();
+        let mut pool = GpuPool::default();
+        let key = key("instance");
+
+        let first = pool.buffer(&device, key, "test", 64, USAGE);
+
+        for _ in 0..5 {
+            let again = pool.buffer(&device, key, "test", 32, USAGE);
+            pool.end_frame();
+
+            assert!(Arc::ptr_eq(&first, &again));
+            assert_eq!(pool.buffer_count(), 1);
+        }
+
+        let larger = pool.buffer(&device, key, "test", 128, USAGE);
+        assert!(!Arc::ptr_eq(&first, &larger));
+
+        let other_usage = pool.buffer(&device, key, "test", 128, BufferUsages::UNIFORM);
+        assert!(!Arc::ptr_eq(&larger, &other_usage));
+
+        assert_eq!(pool.buffer_count(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a GPU adapter"]
+    async fn textures_are_reused_with_the_same_spec() {
+        let (device, _queue) = request_headless_device().await.unwrap();
+        let mut pool = GpuPool::default();
+        let key = key("texture");
+        let (format, usage) = (TextureFormat::Rgba8Unorm, TextureUsages::TEXTURE_BINDING);
+
+        let first = pool.texture(&device, key, "test", extent(4), format, usage);
+
+        for _ in 0..5 {
+            let again = pool.texture(&device, key, "test", extent(4), format, usage);
+            pool.end_frame();
+
+            assert!(Arc::ptr_eq(&first, &again));
+            assert_eq!(pool.texture_count(), 1);
+        }
+
+        let resized = pool.texture(&device, key, "test", extent(8), format, usage);
+        assert!(!Arc::ptr_eq(&first, &resized));
+        assert_eq!(pool.texture_count(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a GPU adapter"]
+    async fn dropped_allocations_are_evicted_after_max_unused_frames() {
+        let (device, _queue) = request_headless_device().await.unwrap();
+        let mut pool = GpuPool::default();
+
+        let held = pool.buffer(&device, key("held"), "test", 64, USAGE);
+        drop(pool.buffer(&device, key("dropped"), "test", 64, USAGE));
+
+        for _ in 0..GpuPool::MAX_UNUSED_FRAMES {
+            pool.end_frame();
+            assert_eq!(pool.buffer_count(), 2);
+        }
+
+        pool.end_frame();
+        assert_eq!(pool.buffer_count(), 1);
+
+        for _ in 0..GpuPool::MAX_UNUSED_FRAMES * 2 {
+            pool.end_frame();
+        }
+
+        assert_eq!(pool.buffer_count(), 1);
+
+        drop(held);
+        pool.end_frame();
+        assert_eq!(pool.buffer_count(), 0);
+    }
+}
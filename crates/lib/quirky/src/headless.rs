@@ -0,0 +1,45 @@
+use anyhow::anyhow;
+use wgpu::{Backends, Device, Instance, InstanceDescriptor, Queue};
+
+/// Requests a device suitable for offscreen rendering, without any surface.
+///
+/// The fallback (software) adapter is preferred, so that rendering is reproducible on machines
+/// without a GPU; if none is available, any adapter is used instead.
+pub async fn request_headless_device() -> anyhow::Result<(Device, Queue)> {
+    let instance = Instance::new(InstanceDescriptor {
+        backends: wgpu::util::backend_bits_from_env().unwrap_or(Backends::all()),
+        dx12_shader_compiler: Default::default(),
+    });
+
+    let adapter = match instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: Default::default(),
+            force_fallback_adapter: true,
+            compatible_surface: None,
+        })
+        .await
+    {
+        Some(adapter) => adapter,
+        None => instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: Default::default(),
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await
+            .ok_or_else(|| anyhow!("no wgpu adapter available for headless rendering"))?,
+    };
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("quirky headless device"),
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+            },
+            None,
+        )
+        .await?;
+
+    Ok((device, queue))
+}
@@ -0,0 +1,311 @@
+use crate::quirky_app_context::QuirkyAppContext;
+use crate::widget::Widget;
+use crate::widgets::events::{
+    KeyCode, KeyboardEvent, KeyboardModifier, MouseButton, MouseEvent, WidgetEvent,
+};
+use crate::QuirkyApp;
+use glam::UVec2;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Hit testing and event delivery, everything the [`InputDriver`] needs to route input.
+///
+/// Pointer positions are in the logical units widgets are laid out in.
+pub trait InputTarget {
+    fn get_widgets_at(&self, pos: UVec2) -> Option<Vec<Uuid>>;
+    fn dispatch_event_to_widget(&self, target: Uuid, event: WidgetEvent);
+
+    /// Called with every new pointer position, before any event is dispatched for it
+    fn pointer_moved(&self, _pos: UVec2) {}
+
+    /// Physical pixels per logical unit, the unit of the positions the target hit tests
+    fn scale_factor(&self) -> f64 {
+        1.0
+    }
+}
+
+impl InputTarget for QuirkyApp {
+    fn get_widgets_at(&self, pos: UVec2) -> Option<Vec<Uuid>> {
+        QuirkyApp::get_widgets_at(self, pos)
+    }
+
+    fn dispatch_event_to_widget(&self, target: Uuid, event: WidgetEvent) {
+        QuirkyApp::dispatch_event_to_widget(self, target, event)
+    }
+
+    fn pointer_moved(&self, pos: UVec2) {
+        self.pointer_position.set(Some(pos));
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.scale_factor.get()
+    }
+}
+
+impl<T: InputTarget + ?Sized> InputTarget for Arc<T> {
+    fn get_widgets_at(&self, pos: UVec2) -> Option<Vec<Uuid>> {
+        self.as_ref().get_widgets_at(pos)
+    }
+
+    fn dispatch_event_to_widget(&self, target: Uuid, event: WidgetEvent) {
+        self.as_ref().dispatch_event_to_widget(target, event)
+    }
+
+    fn pointer_moved(&self, pos: UVec2) {
+        self.as_ref().pointer_moved(pos)
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.as_ref().scale_factor()
+    }
+}
+
+/// Routes input straight into a widget tree, for driving widgets without a [`QuirkyApp`].
+pub struct WidgetTreeTarget<'a> {
+    pub root: Arc<dyn Widget>,
+    pub context: &'a QuirkyAppContext,
+}
+
+impl<'a> WidgetTreeTarget<'a> {
+    pub fn new(root: Arc<dyn Widget>, context: &'a QuirkyAppContext) -> Self {
+        Self { root, context }
+    }
+}
+
+impl InputTarget for WidgetTreeTarget<'_> {
+    fn get_widgets_at(&self, pos: UVec2) -> Option<Vec<Uuid>> {
+        self.context.layers.get_widgets_at(&self.root, pos)
+    }
+
+    fn dispatch_event_to_widget(&self, target: Uuid, event: WidgetEvent) {
+        self.context
+            .dispatch_event(target, event)
+            .expect("failed dispatching event");
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.context.scale_factor.get()
+    }
+}
+
+/// Turns pointer and keyboard actions into the widget events a windowing backend produces.
+///
+/// Tracks hover and press state, so that moving the pointer sends `Leave` to the previously
+/// hovered widget and `Drag` to the widget the button was pressed on, until it is released.
+/// Keyboard events go to the hovered widget, and are rerouted to the focused one by the context.
+pub struct InputDriver<T: InputTarget> {
+    target: T,
+    hovered: Option<Uuid>,
+    pressed: Option<(Uuid, MouseButton)>,
+    pointer_pos: Option<UVec2>,
+}
+
+impl<T: InputTarget> InputDriver<T> {
+    pub fn new(target: T) -> Self {
+        Self {
+            target,
+            hovered: None,
+            pressed: None,
+            pointer_pos: None,
+        }
+    }
+
+    pub fn target(&self) -> &T {
+        &self.target
+    }
+
+    pub fn hovered(&self) -> Option<Uuid> {
+        self.hovered
+    }
+
+    pub fn move_to(&mut self, pos: UVec2) {
+        self.target.pointer_moved(pos);
+
+        if let (Some((pressed, button)), Some(from)) = (self.pressed, self.pointer_pos) {
+            self.target.dispatch_event_to_widget(
+                pressed,
+                WidgetEvent::MouseEvent {
+                    event: MouseEvent::Drag {
+                        from,
+                        to: pos,
+                        button,
+                    },
+                },
+            );
+        }
+
+        if let Some(widget) = self.widget_at(pos) {
+            if let Some(previous) = self.hovered {
+                if previous != widget {
+                    self.target.dispatch_event_to_widget(
+                        previous,
+                        WidgetEvent::MouseEvent {
+                            event: MouseEvent::Leave {},
+                        },
+                    );
+                }
+            }
+
+            self.hovered = Some(widget);
+
+            self.target.dispatch_event_to_widget(
+                widget,
+                WidgetEvent::MouseEvent {
+                    event: MouseEvent::Move { pos },
+                },
+            );
+        }
+
+        self.pointer_pos = Some(pos);
+    }
+
+    /// Presses `button` on the widget at `pos`, without moving the pointer there first.
+    pub fn button_down(&mut self, pos: UVec2, button: MouseButton) {
+        let Some(widget) = self.widget_at(pos) else {
+            return;
+        };
+
+        self.pointer_pos = Some(pos);
+        self.pressed = Some((widget, button));
+
+        self.target.dispatch_event_to_widget(
+            widget,
+            WidgetEvent::MouseEvent {
+                event: MouseEvent::ButtonDown { button },
+            },
+        );
+    }
+
+    /// Releases the pressed button on the widget it was pressed on, wherever the pointer is.
+    pub fn button_up(&mut self) {
+        if let Some((widget, button)) = self.pressed.take() {
+            self.target.dispatch_event_to_widget(
+                widget,
+                WidgetEvent::MouseEvent {
+                    event: MouseEvent::ButtonUp { button },
+                },
+            );
+        }
+    }
+
+    pub fn click(&mut self, pos: UVec2, button: MouseButton) {
+        self.move_to(pos);
+        self.button_down(pos, button);
+        self.button_up();
+    }
+
+    pub fn drag(&mut self, from: UVec2, to: UVec2, button: MouseButton) {
+        self.move_to(from);
+        self.button_down(from, button);
+        self.move_to(to);
+        self.button_up();
+    }
+
+    pub fn press(&mut self, key_code: KeyCode, modifier: KeyboardModifier) {
+        self.target.dispatch_event_to_widget(
+            self.hovered.unwrap_or(Uuid::nil()),
+            WidgetEvent::KeyboardEvent {
+                event: KeyboardEvent::KeyPressed { key_code, modifier },
+            },
+        );
+    }
+
+    /// Presses the keys producing `text`, holding shift for upper case letters and symbols.
+    ///
+    /// Fails before sending anything if a character has no key on a us layout.
+    pub fn type_text(&mut self, text: &str) -> anyhow::Result<()> {
+        let keys = text
+            .chars()
+            .map(|c| key_for_char(c).ok_or_else(|| anyhow::anyhow!("cannot type {c:?}")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        for (key_code, shift) in keys {
+            self.press(
+                key_code,
+                KeyboardModifier {
+                    shift,
+                    ..Default::default()
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    fn widget_at(&self, pos: UVec2) -> Option<Uuid> {
+        self.target
+            .get_widgets_at(pos)
+            .and_then(|widgets| widgets.first().copied())
+    }
+}
+
+const LETTER_KEYS: [KeyCode; 26] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+];
+
+const DIGIT_KEYS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// The key and shift state typing `c`
+fn key_for_char(c: char) -> Option<(KeyCode, bool)> {
+    let key = match c {
+        'a'..='z' => (LETTER_KEYS[(c as u8 - b'a') as usize], false),
+        'A'..='Z' => (LETTER_KEYS[(c as u8 - b'A') as usize], true),
+        '0'..='9' => (DIGIT_KEYS[(c as u8 - b'0') as usize], false),
+        '!' => (KeyCode::Key1, true),
+        '#' => (KeyCode::Key3, true),
+        '$' => (KeyCode::Key4, true),
+        '%' => (KeyCode::Key5, true),
+        '^' => (KeyCode::Key6, true),
+        '&' => (KeyCode::Key7, true),
+        '(' => (KeyCode::Key9, true),
+        ')' => (KeyCode::Key0, true),
+        '*' => (KeyCode::Asterisk, false),
+        '@' => (KeyCode::At, false),
+        ' ' => (KeyCode::Space, false),
+        ',' => (KeyCode::Comma, false),
+        '.' => (KeyCode::Period, false),
+        '\\' => (KeyCode::Backslash, false),
+        ':' => (KeyCode::Colon, false),
+        ';' => (KeyCode::Semicolon, false),
+        '\n' => (KeyCode::Return, false),
+        _ => return None,
+    };
+
+    Some(key)
+}
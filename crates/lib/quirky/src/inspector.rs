@@ -0,0 +1,107 @@
+use crate::layers::Layer;
+use crate::widget::{SizeConstraint, Widget};
+use crate::LayoutBox;
+use futures::task::noop_waker_ref;
+use futures_signals::signal::Signal;
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use uuid::Uuid;
+
+/// The state of a widget and its children at the time the snapshot was taken.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WidgetSnapshot {
+    pub type_name: String,
+    pub id: Uuid,
+    pub bounding_box: LayoutBox,
+    pub dirty: bool,
+    /// The current size constraint, if the widget has one available without waiting
+    pub size_constraint: Option<SizeConstraint>,
+    /// Number of primitives cached from the last draw
+    pub cached_primitives: usize,
+    pub children: Vec<WidgetSnapshot>,
+}
+
+impl WidgetSnapshot {
+    pub fn of(widget: &Arc<dyn Widget>) -> Self {
+        Self {
+            type_name: widget.type_name().to_string(),
+            id: widget.id(),
+            bounding_box: widget.bounding_box().get(),
+            dirty: widget.dirty().get(),
+            size_constraint: current_value(widget.size_constraint()),
+            cached_primitives: widget.cached_primitive_count(),
+            children: widget
+                .children()
+                .map(|children| children.iter().map(Self::of).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn find(&self, id: Uuid) -> Option<&WidgetSnapshot> {
+        if self.id == id {
+            return Some(self);
+        }
+
+        self.children.iter().find_map(|child| child.find(id))
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// The widget tree of one layer root, see [`crate::layers::Layers::roots`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerSnapshot {
+    pub layer: Layer,
+    pub tree: WidgetSnapshot,
+}
+
+/// Polls a signal once, returning its value if it is immediately available
+fn current_value<T>(mut signal: Box<dyn Signal<Item = T> + Unpin + Send>) -> Option<T> {
+    match Pin::new(&mut signal).poll_change(&mut Context::from_waker(noop_waker_ref())) {
+        Poll::Ready(value) => value,
+        Poll::Pending => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::drawable_tree::test::TestWidget;
+    use crate::inspector::WidgetSnapshot;
+    use crate::widget::{SizeConstraint, Widget, WidgetBase};
+    use crate::LayoutBox;
+    use glam::vec2;
+    use std::sync::Arc;
+
+    #[test]
+    fn snapshots_describe_widgets_and_their_children() {
+        let child: Arc<dyn Widget> = TestWidget::new(1.0, 2.0, vec![]);
+        let parent: Arc<dyn Widget> = TestWidget::new(0.0, 0.0, vec![child.clone()]);
+
+        let snapshot = WidgetSnapshot::of(&parent);
+
+        assert_eq!(snapshot.type_name, "TestWidget");
+        assert_eq!(snapshot.id, parent.id());
+        assert!(snapshot.dirty);
+        assert_eq!(
+            snapshot.size_constraint,
+            Some(SizeConstraint::Unconstrained)
+        );
+        assert_eq!(snapshot.cached_primitives, 0);
+        assert_eq!(snapshot.children.len(), 1);
+        assert_eq!(
+            snapshot.find(child.id()).unwrap().bounding_box,
+            LayoutBox {
+                pos: vec2(1.0, 2.0),
+                size: vec2(10.0, 10.0),
+            }
+        );
+
+        let json = snapshot.to_json().unwrap();
+
+        assert!(json.contains("\"type_name\": \"TestWidget\""));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::widget::{hit_test, Widget};
+use futures_signals::signal_vec::MutableVec;
+use glam::UVec2;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Stacked widget trees, each drawn above the ones before it regardless of tree order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Layer {
+    /// The root widget of the app, followed by anything registered here
+    Base,
+    /// Modals and other content covering the whole ui
+    Overlay,
+    /// Dropdowns and context menus
+    Popup,
+    Tooltip,
+    /// Debugging visuals
+    Debug,
+}
+
+impl Layer {
+    /// All layers, in drawing order
+    pub const ALL: [Layer; 5] = [
+        Layer::Base,
+        Layer::Overlay,
+        Layer::Popup,
+        Layer::Tooltip,
+        Layer::Debug,
+    ];
+
+    /// Whether widgets in this layer can be hit by the pointer.
+    ///
+    /// Tooltips and debug visuals never capture input meant for the widgets below them.
+    pub fn receives_input(&self) -> bool {
+        !matches!(self, Layer::Tooltip | Layer::Debug)
+    }
+}
+
+/// The subtrees registered into each [`Layer`].
+#[derive(Default)]
+pub struct Layers {
+    layers: [MutableVec<Arc<dyn Widget>>; Layer::ALL.len()],
+}
+
+impl Layers {
+    pub fn layer(&self, layer: Layer) -> &MutableVec<Arc<dyn Widget>> {
+        &self.layers[layer as usize]
+    }
+
+    /// Adds a subtree on top of the others in `layer`
+    pub fn add(&self, layer: Layer, widget: Arc<dyn Widget>) {
+        self.layer(layer).lock_mut().push_cloned(widget);
+    }
+
+    /// Removes a subtree from `layer`, returning whether it was registered there
+    pub fn remove(&self, layer: Layer, widget_id: Uuid) -> bool {
+        let mut widgets = self.layer(layer).lock_mut();
+        let count = widgets.len();
+
+        widgets.retain(|widget| widget.id() != widget_id);
+
+        widgets.len() != count
+    }
+
+    /// The roots of all subtrees in drawing order, with `base` first
+    pub fn roots(&self, base: &Arc<dyn Widget>) -> Vec<(Layer, Arc<dyn Widget>)> {
+        let mut roots = vec![(Layer::Base, base.clone())];
+
+        for layer in Layer::ALL {
+            roots.extend(
+                self.layer(layer)
+                    .lock_ref()
+                    .iter()
+                    .map(|widget| (layer, widget.clone())),
+            );
+        }
+
+        roots
+    }
+
+    /// Hit tests the layers from the top down, returning the path to the first widget hit
+    pub fn get_widgets_at(&self, base: &Arc<dyn Widget>, pos: UVec2) -> Option<Vec<Uuid>> {
+        self.roots(base)
+            .iter()
+            .rev()
+            .filter(|(layer, _)| layer.receives_input())
+            .find_map(|(_, widget)| hit_test(widget, pos.as_vec2(), vec![]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::drawable_tree::test::TestWidget;
+    use crate::layers::Layer;
+    use crate::quirky_app_context::QuirkyAppContext;
+    use crate::widget::{Widget, WidgetBase};
+    use crate::LayoutBox;
+    use futures_signals::signal::Mutable;
+    use glam::{uvec2, vec2, UVec2};
+    use std::sync::Arc;
+
+    #[test]
+    fn higher_layers_are_hit_first() {
+        let (tx, _rx) = async_std::channel::unbounded();
+        let ctx = QuirkyAppContext::new(
+            Mutable::new(UVec2::new(100, 100)).read_only(),
+            tx,
+            Default::default(),
+        );
+
+        let base: Arc<dyn Widget> = TestWidget::new(0.0, 0.0, vec![]);
+        let popup: Arc<dyn Widget> = TestWidget::new(10.0, 10.0, vec![]);
+        let tooltip: Arc<dyn Widget> = TestWidget::new(0.0, 0.0, vec![]);
+
+        base.set_bounding_box(LayoutBox {
+            pos: vec2(0.0, 0.0),
+            size: vec2(100.0, 100.0),
+        });
+        popup.set_bounding_box(LayoutBox {
+            pos: vec2(10.0, 10.0),
+            size: vec2(20.0, 20.0),
+        });
+        tooltip.set_bounding_box(LayoutBox {
+            pos: vec2(0.0, 0.0),
+            size: vec2(100.0, 100.0),
+        });
+
+        ctx.add_to_layer(Layer::Popup, popup.clone());
+        ctx.add_to_layer(Layer::Tooltip, tooltip.clone());
+
+        assert_eq!(
+            ctx.layers.get_widgets_at(&base, uvec2(15, 15)),
+            Some(vec![popup.id()])
+        );
+        assert_eq!(
+            ctx.layers.get_widgets_at(&base, uvec2(50, 50)),
+            Some(vec![base.id()])
+        );
+
+        assert!(ctx.remove_from_layer(Layer::Popup, popup.id()));
+        assert_eq!(
+            ctx.layers.get_widgets_at(&base, uvec2(15, 15)),
+            Some(vec![base.id()])
+        );
+    }
+}
@@ -1,35 +1,77 @@
+mod batching;
+mod camera_groups;
+mod clip_stack;
+mod damage;
+mod debug_overlay;
+#[cfg(feature = "devtools")]
+pub mod devtools;
+pub mod display_list;
 pub mod drawable_primitive;
+pub mod drawable_tree;
+pub mod frame_stats;
+pub mod gpu_pool;
+pub mod headless;
+pub mod input_driver;
+pub mod inspector;
+pub mod layers;
+pub mod opacity_groups;
+pub mod pipeline_registry;
+pub mod prepared_frame;
 pub mod quirky_app_context;
+pub mod recording;
 pub mod render_contexts;
+mod retained;
+#[cfg(feature = "shader-hot-reload")]
+mod shader_hot_reload;
+pub mod shader_source;
 mod ui_camera;
 pub mod widget;
 pub mod widgets;
 
+use crate::batching::{ClippedPrimitive, InstanceBatcher};
+use crate::camera_groups::CameraGroups;
+use crate::damage::DamageTracker;
+use crate::debug_overlay::{DebugOverlay, HIGHLIGHT_COLOR, HIT_PATH_COLOR};
+use crate::display_list::{collect_display_list, DisplayList};
+use crate::drawable_tree::{DrawableTree, TreeUpdate};
+use crate::frame_stats::FrameStats;
+use crate::gpu_pool::GpuPool;
+use crate::inspector::{LayerSnapshot, WidgetSnapshot};
+use crate::layers::Layer;
+use crate::opacity_groups::{GroupComposite, OpacityGroups};
+use crate::pipeline_registry::{PipelineContext, PipelineRegistry, PipelineTarget};
+use crate::prepared_frame::{LoadBehaviour, PreparedFrame};
 use crate::quirky_app_context::QuirkyResources;
+use crate::recording::{replay, InputRecording, Recorder, ReplayPacing};
+use crate::retained::RetainedFrame;
 use crate::ui_camera::UiCamera2D;
 use async_std::task::sleep;
-use drawable_primitive::DrawablePrimitive;
 use futures::stream::FuturesUnordered;
 use futures::FutureExt;
 use futures::StreamExt;
-use futures_signals::signal::{Mutable, SignalExt};
+use futures_signals::map_ref;
+use futures_signals::signal::{Mutable, ReadOnlyMutable, SignalExt};
 use futures_signals::signal_vec::MutableVec;
-use glam::UVec2;
+use glam::{Affine2, UVec2, Vec2};
+use image::RgbaImage;
 use quirky_app_context::QuirkyAppContext;
 use render_contexts::PrepareContext;
 use render_contexts::RenderContext;
+use serde::{Deserialize, Serialize};
 use std::borrow::BorrowMut;
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::iter;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, Device, Queue,
-    RenderPipeline, ShaderStages, Surface, SurfaceTexture, TextureFormat, TextureView,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages,
+    Device, Extent3d, ImageCopyBuffer, ImageDataLayout, Queue, ShaderStages, Surface,
+    SurfaceTexture, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureView,
 };
 use widget::Widget;
 use widgets::events::WidgetEvent;
@@ -43,11 +85,19 @@ macro_rules! clone {
     }};
 }
 
+/// How long [`QuirkyApp::replay_recording`] waits for the widgets to settle after a resize
+const REPLAY_SETTLE_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct QuirkyApp {
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
     pub context: QuirkyAppContext,
+    /// The size of the target in physical pixels
     pub viewport_size: Mutable<UVec2>,
+    /// Physical pixels per logical unit, the widgets being laid out in logical units
+    pub scale_factor: Mutable<f64>,
     pub resources: Arc<Mutex<QuirkyResources>>,
-    pipeline_cache: Mutex<HashMap<Uuid, RenderPipeline>>,
+    pipelines: Mutex<PipelineRegistry>,
     bind_group_cache: Mutex<HashMap<Uuid, BindGroup>>,
     ui_camera: Mutex<UiCamera2D>,
     surface_format: TextureFormat,
@@ -56,6 +106,28 @@ pub struct QuirkyApp {
     camera_bind_group: BindGroup,
     signal_dirty_rx: async_std::channel::Receiver<()>,
     widget: Arc<dyn Widget>,
+    recorder: Mutex<Option<Recorder>>,
+    /// A widget to outline on top of the ui, for debugging tools
+    pub highlighted_widget: Mutable<Option<Uuid>>,
+    /// Outlines every widget box, and the widgets hit under the pointer
+    pub debug_overlay_enabled: Mutable<bool>,
+    /// The last pointer position reported by the input backend
+    pub pointer_position: Mutable<Option<UVec2>>,
+    /// What the target holds before the ui is drawn onto it
+    pub load_behaviour: Mutable<LoadBehaviour>,
+    /// Samples per pixel the ui is drawn with before being resolved, 1 disables multisampling.
+    ///
+    /// Counts other than 1 and 4 depend on the adapter and surface format.
+    pub sample_count: Mutable<u32>,
+    debug_overlay: Mutex<DebugOverlay>,
+    batcher: Mutex<InstanceBatcher>,
+    camera_groups: Mutex<CameraGroups>,
+    opacity_groups: Mutex<OpacityGroups>,
+    gpu_pool: Mutex<GpuPool>,
+    damage: Mutex<DamageTracker>,
+    drawable_tree: Mutex<DrawableTree>,
+    retained_frame: Mutex<RetainedFrame>,
+    frame_stats: Mutable<FrameStats>,
 }
 
 impl QuirkyApp {
@@ -70,6 +142,7 @@ impl QuirkyApp {
         let queue = queue.into();
 
         let viewport_size = Mutable::new(Default::default());
+        let scale_factor = Mutable::new(1.0);
         let ui_camera = UiCamera2D::default();
         let (camera_buffer, camera_bind_group_layout, camera_bind_group) =
             Self::setup(&device, &ui_camera);
@@ -77,23 +150,38 @@ impl QuirkyApp {
         let (tx, rx) = async_std::channel::unbounded();
 
         let resources: Arc<Mutex<QuirkyResources>> = Mutex::new(Default::default()).into();
-        let context = QuirkyAppContext::new(
-            device,
-            queue,
-            viewport_size.read_only(),
-            tx,
-            resources.clone(),
-        );
+        let context = QuirkyAppContext::new(viewport_size.read_only(), tx, resources.clone())
+            .with_scale_factor(scale_factor.read_only());
+
+        let retained_frame = RetainedFrame::new(&device, surface_format);
+        let opacity_groups = OpacityGroups::new(&device, surface_format);
 
         init_fn(&mut resources.lock().unwrap(), &context, surface_format);
+
+        let mut pipelines = PipelineRegistry::default();
+        pipelines.register_all(std::mem::take(
+            &mut resources.lock().unwrap().pipeline_kinds,
+        ));
+        pipelines.warm_up(&PipelineContext::new(
+            &device,
+            PipelineTarget {
+                format: surface_format,
+                sample_count: 1,
+            },
+            &camera_bind_group_layout,
+        ));
+
         let widget = ui_factory(resources.clone());
 
         Self {
+            device,
+            queue,
             context,
             viewport_size,
+            scale_factor,
             widget,
             resources,
-            pipeline_cache: Mutex::new(Default::default()),
+            pipelines: pipelines.into(),
             bind_group_cache: Mutex::new(Default::default()),
             ui_camera: ui_camera.into(),
             surface_format,
@@ -101,6 +189,21 @@ impl QuirkyApp {
             camera_bind_group_layout,
             camera_bind_group,
             signal_dirty_rx: rx,
+            recorder: Default::default(),
+            highlighted_widget: Default::default(),
+            debug_overlay_enabled: Default::default(),
+            pointer_position: Default::default(),
+            load_behaviour: Default::default(),
+            sample_count: Mutable::new(1),
+            debug_overlay: Default::default(),
+            batcher: Default::default(),
+            camera_groups: Default::default(),
+            opacity_groups: opacity_groups.into(),
+            gpu_pool: Default::default(),
+            damage: Default::default(),
+            drawable_tree: Default::default(),
+            retained_frame: retained_frame.into(),
+            frame_stats: Default::default(),
         }
     }
 
@@ -111,6 +214,17 @@ impl QuirkyApp {
         let mut run_futs = FuturesUnordered::new();
 
         run_futs.push(fut.boxed());
+
+        for layer in Layer::ALL {
+            run_futs.push(
+                run_widget::run_widgets(
+                    &self.context,
+                    self.context.layers.layer(layer).signal_vec_cloned(),
+                )
+                .boxed(),
+            );
+        }
+
         run_futs.push(
             futures_signals::signal::from_stream(self.signal_dirty_rx.clone())
                 .throttle(|| sleep(Duration::from_millis(16)))
@@ -122,117 +236,546 @@ impl QuirkyApp {
         );
 
         run_futs.push(
-            self.viewport_size
+            map_ref! {
+                let _highlighted = self.highlighted_widget.signal(),
+                let _enabled = self.debug_overlay_enabled.signal() => {}
+            }
+            .for_each(|_| self.context.signal_redraw())
+            .boxed(),
+        );
+
+        #[cfg(feature = "shader-hot-reload")]
+        run_futs.push(self.reload_shaders().boxed());
+
+        run_futs.push(
+            self.sample_count
                 .signal()
-                .throttle(|| async_std::task::sleep(Duration::from_millis(5)))
-                .for_each(|new_viewport_size| {
-                    self.ui_camera
-                        .lock()
-                        .unwrap()
-                        .resize_viewport(new_viewport_size);
-
-                    self.widget.set_bounding_box(LayoutBox {
-                        pos: Default::default(),
-                        size: new_viewport_size,
-                    });
+                .for_each(|_| self.context.signal_redraw())
+                .boxed(),
+        );
 
-                    async move {}
+        run_futs.push(
+            self.pointer_position
+                .signal()
+                .for_each(|_| async {
+                    if self.debug_overlay_enabled.get() {
+                        self.context.signal_redraw().await;
+                    }
                 })
                 .boxed(),
         );
 
+        run_futs.push(
+            map_ref! {
+                let viewport_size = self.viewport_size.signal(),
+                let scale_factor = self.scale_factor.signal() => (*viewport_size, *scale_factor)
+            }
+            .throttle(|| async_std::task::sleep(Duration::from_millis(5)))
+            .for_each(|(new_viewport_size, scale_factor)| {
+                self.apply_viewport_size(new_viewport_size, scale_factor);
+                async move {}
+            })
+            .boxed(),
+        );
+
         loop {
             run_futs.select_next_some().await;
         }
     }
 
+    /// Reloads the pipelines whenever a watched shader changes, redrawing everything with them
+    #[cfg(feature = "shader-hot-reload")]
+    async fn reload_shaders(&self) {
+        let dirs = self.resources.lock().unwrap().shader_dirs.clone();
+        let mut watcher = shader_hot_reload::ShaderWatcher::new(dirs);
+
+        loop {
+            sleep(Duration::from_millis(500)).await;
+
+            if !watcher.poll() {
+                continue;
+            }
+
+            tracing::info!("shaders changed, reloading pipelines");
+
+            self.pipelines
+                .lock()
+                .unwrap()
+                .reload(&self.device, &self.camera_bind_group_layout);
+
+            for (_, root) in self.context.layers.roots(&self.widget) {
+                set_subtree_dirty(&root);
+            }
+
+            self.context.signal_redraw().await;
+        }
+    }
+
+    fn apply_viewport_size(&self, new_viewport_size: UVec2, scale_factor: f64) {
+        self.ui_camera
+            .lock()
+            .unwrap()
+            .resize_viewport(new_viewport_size, scale_factor);
+
+        self.widget.set_bounding_box(LayoutBox {
+            pos: Default::default(),
+            size: logical_size(new_viewport_size, scale_factor),
+        });
+    }
+
+    /// Renders the UI into an offscreen texture of the given size, and reads the result back
+    /// into an image.
+    ///
+    /// The viewport is resized to `size` physical pixels before drawing, using the same prepare
+    /// and draw path as [`QuirkyApp::draw`]. The app must have been created with an 8 bit rgba or
+    /// bgra format.
+    pub fn render_to_image(&self, size: UVec2) -> anyhow::Result<RgbaImage> {
+        if size.x == 0 || size.y == 0 {
+            anyhow::bail!("cannot render to an empty image of size {size}");
+        }
+
+        let swizzle = match self.surface_format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => anyhow::bail!("unsupported format for offscreen rendering: {format:?}"),
+        };
+
+        if self.viewport_size.get() != size {
+            self.viewport_size.set(size);
+            self.apply_viewport_size(size, self.scale_factor.get());
+        }
+
+        let texture = self.device.create_texture(&TextureDescriptor {
+            label: Some("quirky offscreen target"),
+            size: Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.surface_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        self.draw(&texture.create_view(&Default::default()))?;
+
+        self.read_back_texture(&texture, size, swizzle)
+    }
+
+    fn read_back_texture(
+        &self,
+        texture: &Texture,
+        size: UVec2,
+        swizzle: bool,
+    ) -> anyhow::Result<RgbaImage> {
+        let unpadded_bytes_per_row = size.x * 4;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("quirky offscreen readback"),
+            size: (padded_bytes_per_row * size.y) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("quirky offscreen readback"),
+            });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(size.y),
+                },
+            },
+            texture.size(),
+        );
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv()??;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * size.y) as usize);
+
+        {
+            let mapped = slice.get_mapped_range();
+
+            for row in mapped.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+
+        buffer.unmap();
+
+        if swizzle {
+            pixels.chunks_exact_mut(4).for_each(|p| p.swap(0, 2));
+        }
+
+        RgbaImage::from_raw(size.x, size.y, pixels)
+            .ok_or_else(|| anyhow::anyhow!("readback buffer does not match the image size"))
+    }
+
+    /// Draws the ui onto `view`, which should be the size of the viewport.
+    ///
+    /// What `view` holds beforehand is decided by [`QuirkyApp::load_behaviour`].
     pub fn draw(&self, view: &TextureView) -> anyhow::Result<()> {
-        let camera_uniform = self.ui_camera.lock().unwrap().create_camera_uniform();
+        let frame = self.prepare_frame()?;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("quirky present"),
+            });
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("quirky present"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: frame.load_op(),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            frame.draw_into_pass(&mut pass);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        Ok(())
+    }
+
+    /// Prepares the widgets and redraws the damaged part of the ui, submitting that work.
+    ///
+    /// The returned frame composites the ui into a render pass the host application owns, with
+    /// [`PreparedFrame::draw_into_pass`]. The pass target should be the size of the viewport, and
+    /// the host's commands need to be submitted after this call returns.
+    pub fn prepare_frame(&self) -> anyhow::Result<PreparedFrame<'_>> {
+        let _span = tracing::info_span!("draw").entered();
+        let frame_start = Instant::now();
+        let mut stats = FrameStats {
+            frame: self.frame_stats.lock_ref().frame + 1,
+            ..Default::default()
+        };
+
+        let ui_camera = self.ui_camera.lock().unwrap();
+        let camera_uniform = ui_camera.create_camera_uniform();
         let screen_resolution = self.context.viewport_size.get();
+        let scale_factor = self.context.scale_factor.get();
+        let sample_count = self.sample_count.get();
 
-        self.context.queue.write_buffer(
+        self.queue.write_buffer(
             &self.camera_uniform_buffer,
             0,
             bytemuck::cast_slice(&[camera_uniform]),
         );
 
-        let mut encoder =
-            self.context
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("hi there"),
-                });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("hi there"),
+            });
 
-        let mut pipeline_cache = self.pipeline_cache.lock().unwrap();
+        let mut pipelines = self.pipelines.lock().unwrap();
         let mut bind_group_cache = self.bind_group_cache.lock().unwrap();
         let mut resources = self.resources.lock().unwrap();
-        let mut out_list = VecDeque::with_capacity(10000);
+        let mut debug_overlay = self.debug_overlay.lock().unwrap();
+        let mut batcher = self.batcher.lock().unwrap();
+        let mut camera_groups = self.camera_groups.lock().unwrap();
+        let mut opacity_groups = self.opacity_groups.lock().unwrap();
+        let mut gpu_pool = self.gpu_pool.lock().unwrap();
+        let mut damage = self.damage.lock().unwrap();
+        let mut drawable_tree = self.drawable_tree.lock().unwrap();
+        let mut retained_frame = self.retained_frame.lock().unwrap();
+
+        // Pipelines are created per sample count, and so are the text renderers of retained
+        // primitives, which are prepared again
+        if retained_frame.sample_count() != sample_count {
+            pipelines.warm_up(&PipelineContext::new(
+                &self.device,
+                PipelineTarget {
+                    format: self.surface_format,
+                    sample_count,
+                },
+                &self.camera_bind_group_layout,
+            ));
+
+            for (_, root) in self.context.layers.roots(&self.widget) {
+                set_subtree_dirty(&root);
+            }
+        }
+
+        let contents_lost =
+            retained_frame.ensure_size(&self.device, screen_resolution, scale_factor, sample_count);
+
+        // Before preparing, as that clears the dirty flags
+        self.build_debug_overlay(&mut debug_overlay);
 
         {
             let mut paint_context = PrepareContext {
                 resources: resources.borrow_mut(),
-                device: &self.context.device,
-                queue: &self.context.queue,
+                device: &self.device,
+                queue: &self.queue,
                 surface_format: self.surface_format,
-                pipeline_cache: pipeline_cache.borrow_mut(),
+                screen_resolution,
+                scale_factor,
+                sample_count,
+                pipelines: &mut pipelines,
                 bind_group_cache: bind_group_cache.borrow_mut(),
                 camera_bind_group_layout: &self.camera_bind_group_layout,
+                gpu_pool: &mut gpu_pool,
+                widget_id: Uuid::nil(),
+                primitive_index: 0,
+                transform: Affine2::IDENTITY,
             };
 
-            next_drawable_list(
-                &self.widget,
-                &self.context,
-                &mut paint_context,
-                &mut out_list,
-            );
+            let widget_prepare_start = Instant::now();
 
-            for drawable in out_list.iter_mut() {
-                for d in drawable.1.iter_mut() {
-                    d.prepare(&mut paint_context);
-                }
+            {
+                let _span = tracing::debug_span!("update_drawable_tree").entered();
+
+                let roots: Vec<Arc<dyn Widget>> = self
+                    .context
+                    .layers
+                    .roots(&self.widget)
+                    .into_iter()
+                    .map(|(_, root)| root)
+                    .collect();
+
+                drawable_tree.update(
+                    &roots,
+                    &mut TreeUpdate {
+                        ctx: &self.context,
+                        resources: paint_context.resources,
+                        damage: &mut damage,
+                        stats: &mut stats,
+                    },
+                );
+                drawable_tree.set_target(screen_resolution, scale_factor);
+            }
+
+            let primitive_prepare_start = Instant::now();
+            stats.widget_prepare_time = primitive_prepare_start - widget_prepare_start;
+
+            {
+                let _span = tracing::debug_span!("prepare_primitives").entered();
+
+                stats.prepared_primitives = drawable_tree.prepare_primitives(&mut paint_context);
             }
+
+            stats.primitive_prepare_time = primitive_prepare_start.elapsed();
         }
-        let render_context = RenderContext {
-            resources: resources.borrow_mut(),
-            camera_bind_group: &self.camera_bind_group,
-            screen_resolution,
-            pipeline_cache: &pipeline_cache,
-            bind_group_cache: &bind_group_cache,
+
+        gpu_pool.end_frame();
+
+        let viewport = LayoutBox {
+            pos: Vec2::ZERO,
+            size: screen_resolution.max(UVec2::ONE).as_vec2(),
         };
 
-        {
-            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
+        // Widgets are laid out in logical units, while scissor rects are in physical pixels
+        let frame_damage = damage.finish();
+        stats.damage = if contents_lost {
+            Some(viewport)
+        } else {
+            frame_damage.and_then(|d| d.to_physical(scale_factor).intersect(&viewport))
+        };
+
+        debug_overlay.prepare(
+            &self.device,
+            &self.queue,
+            self.surface_format,
+            &self.camera_bind_group_layout,
+        );
+
+        // Without damage the retained frame is composited as it is, nothing needs drawing
+        if let Some(damaged) = stats.damage {
+            let groups = drawable_tree.groups();
+
+            opacity_groups.prepare(
+                &self.device,
+                &self.queue,
+                screen_resolution,
+                sample_count,
+                groups.iter().map(|g| (g.widget_id, g.group)),
+            );
+
+            let composites: Vec<GroupComposite> = groups
+                .iter()
+                .map(|g| opacity_groups.composite(g.widget_id, g.group.blend_mode))
+                .collect();
+
+            camera_groups.clear();
+
+            // The primitives of the frame, followed by the primitives of each group
+            let mut targets: Vec<Vec<ClippedPrimitive>> =
+                iter::repeat_with(Vec::new).take(groups.len() + 1).collect();
+
+            for (index, d) in drawable_tree.drawables().enumerate() {
+                let camera = camera_groups.group_for(d.transform);
+
+                // A group is composited into its parent where the group widget is drawn
+                if let Some(group) = d.group.filter(|g| groups[*g].first == index) {
+                    targets[groups[group].parent.map_or(0, |p| p + 1)].push(ClippedPrimitive {
+                        primitive: &composites[group],
+                        clip: Some(groups[group].clip.to_physical(scale_factor)),
+                        camera: 0,
+                    });
+                }
+
+                targets[d.group.map_or(0, |g| g + 1)].extend(d.primitives.iter().map(|p| {
+                    ClippedPrimitive {
+                        primitive: p.as_ref(),
+                        clip: d.clip.map(|clip| clip.to_physical(scale_factor)),
+                        camera,
+                    }
+                }));
+            }
+
+            let (primitives, group_primitives) = targets.split_first().unwrap();
+
+            batcher.plan(primitives);
+            batcher.upload(&self.device, &self.queue);
+
+            for (group, primitives) in groups.iter().zip(group_primitives) {
+                let target = opacity_groups.target_mut(group.widget_id);
+
+                target.batcher.plan(primitives);
+                target.batcher.upload(&self.device, &self.queue);
+            }
+
+            camera_groups.upload(
+                &self.device,
+                &self.queue,
+                &self.camera_bind_group_layout,
+                &ui_camera,
+            );
+
+            let render_contexts: Vec<RenderContext> = camera_groups
+                .bind_groups()
+                .map(|camera_bind_group| RenderContext {
+                    resources: &resources,
+                    camera_bind_group,
+                    screen_resolution,
+                    pipelines: &pipelines,
+                    bind_group_cache: &bind_group_cache,
+                })
+                .collect();
+
+            let encode_start = Instant::now();
+
+            {
+                let _span = tracing::debug_span!("encode").entered();
+
+                // Inner groups first, as they are composited into the groups enclosing them
+                for (group, primitives) in groups.iter().zip(group_primitives).rev() {
+                    let Some(scissor) = group.clip.to_physical(scale_factor).intersect(&damaged)
+                    else {
+                        continue;
+                    };
+
+                    let target = opacity_groups.target(group.widget_id);
+
+                    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("quirky opacity group"),
+                        color_attachments: &[Some(target.color_attachment())],
+                        depth_stencil_attachment: None,
+                    });
+
+                    let (x, y, width, height) = scissor.scissor_rect();
+                    pass.set_scissor_rect(x, y, width, height);
+
+                    target.batcher.draw(
+                        &mut pass,
+                        &render_contexts,
+                        primitives,
+                        scissor,
+                        &mut stats,
+                    );
 
-            out_list.iter().for_each(|d| {
-                d.1.iter().for_each(|d2| {
-                    d2.draw(&mut pass, &render_context);
+                    stats.opacity_groups += 1;
+                }
+
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("quirky damage"),
+                    color_attachments: &[Some(retained_frame.color_attachment())],
+                    depth_stencil_attachment: None,
                 });
-            });
+
+                retained_frame.begin_damage(&mut pass, damaged);
+                batcher.draw(&mut pass, &render_contexts, primitives, damaged, &mut stats);
+            }
+
+            stats.encode_time = encode_start.elapsed();
         }
 
-        self.context.queue.submit(iter::once(encoder.finish()));
+        self.queue.submit(iter::once(encoder.finish()));
+
+        stats.total_time = frame_start.elapsed();
+        self.frame_stats.set(stats);
 
-        for d in out_list {
-            d.2.set_cached_primitives(Some(d.1));
+        Ok(PreparedFrame {
+            blit: retained_frame.blit(),
+            overlay: debug_overlay.prepared(),
+            camera_bind_group: &self.camera_bind_group,
+            load_behaviour: self.load_behaviour.get(),
+        })
+    }
+
+    /// Statistics about the last drawn frame, updated after every frame
+    pub fn frame_stats(&self) -> ReadOnlyMutable<FrameStats> {
+        self.frame_stats.read_only()
+    }
+
+    fn build_debug_overlay(&self, debug_overlay: &mut DebugOverlay) {
+        debug_overlay.clear();
+
+        if self.debug_overlay_enabled.get() {
+            for (_, root) in self.context.layers.roots(&self.widget) {
+                debug_overlay.outline_tree(&root, None);
+            }
+
+            let hit_path = self
+                .pointer_position
+                .get()
+                .and_then(|pos| self.get_widgets_at(pos))
+                .unwrap_or_default();
+
+            for widget in hit_path.iter().filter_map(|id| self.find_widget(*id)) {
+                debug_overlay.outline(widget.bounding_box().get(), 2.0, HIT_PATH_COLOR);
+            }
         }
 
-        Ok(())
+        if let Some(highlighted) = self
+            .highlighted_widget
+            .get()
+            .and_then(|id| self.find_widget(id))
+        {
+            debug_overlay.outline(highlighted.bounding_box().get(), 2.0, HIGHLIGHT_COLOR);
+        }
     }
 
     fn setup(device: &Device, camera: &UiCamera2D) -> (Buffer, BindGroupLayout, BindGroup) {
@@ -272,57 +815,258 @@ impl QuirkyApp {
     }
 }
 
-fn next_drawable_list(
-    widget: &Arc<dyn Widget>,
-    ctx: &QuirkyAppContext,
-    paint_ctx: &mut PrepareContext,
-    out: &mut VecDeque<(Uuid, Vec<Box<dyn DrawablePrimitive>>, Arc<dyn Widget>)>,
-) {
-    let widget_id = widget.id();
-
-    if widget.dirty().get() {
-        widget.clear_dirty();
-        out.push_back((widget_id, widget.prepare(ctx, paint_ctx), widget.clone()));
-    } else {
-        out.push_back((
-            widget_id,
-            widget.get_cached_primitives().or(Some(vec![])).unwrap(),
-            widget.clone(),
-        ));
+/// The size in logical units of a target `physical_size` pixels large
+pub fn logical_size(physical_size: UVec2, scale_factor: f64) -> Vec2 {
+    (physical_size.as_dvec2() / scale_factor).as_vec2()
+}
+
+fn find_widget(widget: &Arc<dyn Widget>, id: Uuid) -> Option<Arc<dyn Widget>> {
+    if widget.id() == id {
+        return Some(widget.clone());
     }
 
-    widget.children().map(|v| {
-        v.iter()
-            .for_each(|child| next_drawable_list(child, ctx, paint_ctx, out))
-    });
+    widget
+        .children()?
+        .iter()
+        .find_map(|child| find_widget(child, id))
 }
 
-#[derive(PartialEq, Clone, Copy, Debug, Default)]
+fn set_subtree_dirty(widget: &Arc<dyn Widget>) {
+    widget.set_dirty();
+
+    if let Some(children) = widget.children() {
+        children.iter().for_each(set_subtree_dirty);
+    }
+}
+
+fn is_subtree_dirty(widget: &Arc<dyn Widget>) -> bool {
+    widget.dirty().get()
+        || widget
+            .children()
+            .map(|children| children.iter().any(is_subtree_dirty))
+            .unwrap_or(false)
+}
+
+/// A box in logical units.
+///
+/// Layout keeps fractional positions and sizes, these are only rounded to whole pixels by
+/// [`LayoutBox::snap_to_pixels`] and [`LayoutBox::to_physical`] when drawing.
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
 pub struct LayoutBox {
-    pub pos: UVec2,
-    pub size: UVec2,
+    pub pos: Vec2,
+    pub size: Vec2,
 }
 
 impl LayoutBox {
-    pub fn contains(&self, pos: UVec2) -> bool {
+    pub fn contains(&self, pos: Vec2) -> bool {
         let br = self.pos + self.size;
 
         pos.x >= self.pos.x && pos.y >= self.pos.y && pos.x < br.x && pos.y < br.y
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.size.x <= 0.0 || self.size.y <= 0.0
+    }
+
+    /// The smallest box containing both boxes
+    pub fn union(&self, other: &LayoutBox) -> LayoutBox {
+        let pos = self.pos.min(other.pos);
+        let end = (self.pos + self.size).max(other.pos + other.size);
+
+        LayoutBox {
+            pos,
+            size: end - pos,
+        }
+    }
+
+    /// The area covered by both boxes, if they overlap
+    pub fn intersect(&self, other: &LayoutBox) -> Option<LayoutBox> {
+        let pos = self.pos.max(other.pos);
+        let end = (self.pos + self.size).min(other.pos + other.size);
+
+        (end.x > pos.x && end.y > pos.y).then(|| LayoutBox {
+            pos,
+            size: end - pos,
+        })
+    }
+
+    /// Converts the box to the whole physical pixels it covers, rounding outwards
+    pub fn to_physical(&self, scale_factor: f64) -> LayoutBox {
+        let pos = (self.pos.as_dvec2() * scale_factor).floor();
+        let end = ((self.pos + self.size).as_dvec2() * scale_factor).ceil();
+
+        LayoutBox {
+            pos: pos.as_vec2(),
+            size: (end - pos).as_vec2(),
+        }
+    }
+
+    /// Moves the edges of the box to the nearest physical pixel boundaries, staying in logical
+    /// units, so that primitives drawn at fractional positions stay sharp
+    pub fn snap_to_pixels(&self, scale_factor: f64) -> LayoutBox {
+        let pos = (self.pos.as_dvec2() * scale_factor).round();
+        let end = ((self.pos + self.size).as_dvec2() * scale_factor).round();
+
+        LayoutBox {
+            pos: (pos / scale_factor).as_vec2(),
+            size: ((end - pos) / scale_factor).as_vec2(),
+        }
+    }
+
+    /// The smallest box containing this box once mapped by `transform`
+    pub fn transformed(&self, transform: &Affine2) -> LayoutBox {
+        if *transform == Affine2::IDENTITY {
+            return *self;
+        }
+
+        let corners = [
+            self.pos,
+            self.pos + Vec2::new(self.size.x, 0.0),
+            self.pos + Vec2::new(0.0, self.size.y),
+            self.pos + self.size,
+        ]
+        .map(|corner| transform.transform_point2(corner));
+
+        let min = corners.iter().fold(Vec2::MAX, |min, c| min.min(*c));
+        let max = corners.iter().fold(Vec2::MIN, |max, c| max.max(*c));
+
+        LayoutBox {
+            pos: min,
+            size: max - min,
+        }
+    }
+
+    /// The box as a scissor rect, for a box already in physical pixels
+    pub(crate) fn scissor_rect(&self) -> (u32, u32, u32, u32) {
+        (
+            self.pos.x as u32,
+            self.pos.y as u32,
+            self.size.x as u32,
+            self.size.y as u32,
+        )
+    }
 }
 
 impl QuirkyApp {
+    /// Describes the current widget tree without touching the gpu, for software rendering.
+    pub fn display_list(&self) -> DisplayList {
+        let mut display_list = DisplayList::default();
+
+        for (_, root) in self.context.layers.roots(&self.widget) {
+            collect_display_list(
+                &root,
+                &self.context,
+                &mut self.resources.lock().unwrap(),
+                &mut display_list,
+            );
+        }
+
+        display_list
+    }
+
+    /// Captures the current state of the widget trees of every layer, in drawing order with the
+    /// app's root widget first.
+    pub fn snapshot_tree(&self) -> Vec<LayerSnapshot> {
+        self.context
+            .layers
+            .roots(&self.widget)
+            .iter()
+            .map(|(layer, root)| LayerSnapshot {
+                layer: *layer,
+                tree: WidgetSnapshot::of(root),
+            })
+            .collect()
+    }
+
+    pub fn has_dirty_widgets(&self) -> bool {
+        self.context
+            .layers
+            .roots(&self.widget)
+            .iter()
+            .any(|(_, root)| is_subtree_dirty(root))
+    }
+
+    /// Hit tests the layers from the top down, see [`layers::Layers::get_widgets_at`]
     pub fn get_widgets_at(&self, pos: UVec2) -> Option<Vec<Uuid>> {
-        self.widget.get_widget_at(pos, vec![])
+        self.context.layers.get_widgets_at(&self.widget, pos)
+    }
+
+    fn find_widget(&self, id: Uuid) -> Option<Arc<dyn Widget>> {
+        self.context
+            .layers
+            .roots(&self.widget)
+            .iter()
+            .find_map(|(_, root)| find_widget(root, id))
     }
 
     pub fn dispatch_event_to_widget(&self, target: Uuid, event: WidgetEvent) {
+        if let Some(recorder) = self.recorder.lock().unwrap().as_mut() {
+            recorder.record(target, &event);
+        }
+
         self.context
             .dispatch_event(target, event)
             .expect("failed dispatching event");
     }
 }
 
+impl QuirkyApp {
+    /// Starts recording every event dispatched through [`QuirkyApp::dispatch_event_to_widget`],
+    /// discarding any recording in progress.
+    pub fn start_recording(&self) {
+        let _ = self.recorder.lock().unwrap().insert(Recorder::new(
+            self.viewport_size.get(),
+            self.scale_factor.get(),
+        ));
+    }
+
+    pub fn stop_recording(&self) -> Option<InputRecording> {
+        self.recorder.lock().unwrap().take().map(Recorder::finish)
+    }
+
+    /// Resizes the viewport to the recorded size and scale factor, waits for the widgets to settle
+    /// and replays the recording.
+    ///
+    /// Settling needs frames to be drawn, so the app should be running with a redraw callback
+    /// that draws them, see [`QuirkyApp::settled`].
+    pub async fn replay_recording(&self, recording: &InputRecording, pacing: ReplayPacing) {
+        if self.viewport_size.get() != recording.viewport_size
+            || self.scale_factor.get() != recording.scale_factor
+        {
+            self.viewport_size.set(recording.viewport_size);
+            self.scale_factor.set(recording.scale_factor);
+
+            if async_std::future::timeout(REPLAY_SETTLE_TIMEOUT, self.settled())
+                .await
+                .is_err()
+            {
+                tracing::warn!(
+                    "widgets did not settle within {REPLAY_SETTLE_TIMEOUT:?}, replaying anyway"
+                );
+            }
+        }
+
+        replay(self, recording, pacing).await
+    }
+
+    /// Resolves once a frame was drawn after which no widget is dirty or waiting to be visited.
+    ///
+    /// This only watches the frames, drawing them is left to the host.
+    pub async fn settled(&self) {
+        let first_frame = self.frame_stats.lock_ref().frame;
+        let mut frames = self.frame_stats.signal_ref(|stats| stats.frame).to_stream();
+
+        while let Some(frame) = frames.next().await {
+            if frame > first_frame
+                && !self.has_dirty_widgets()
+                && !self.drawable_tree.lock().unwrap().has_invalidations()
+            {
+                return;
+            }
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! assert_f32_eq {
     ($l:expr, $r:expr, $msg:expr) => {{
@@ -336,3 +1080,41 @@ macro_rules! assert_f32_eq {
         }
     }};
 }
+
+#[cfg(test)]
+mod test {
+    use crate::drawable_tree::test::TestWidget;
+    use crate::headless::request_headless_device;
+    use crate::prepared_frame::LoadBehaviour;
+    use crate::widget::Widget;
+    use crate::QuirkyApp;
+    use glam::uvec2;
+    use image::Rgba;
+    use std::sync::Arc;
+    use wgpu::TextureFormat;
+
+    #[tokio::test]
+    #[ignore = "needs a GPU adapter"]
+    async fn render_to_image_reads_back_rgba_for_both_channel_orders() {
+        let (device, queue) = request_headless_device().await.unwrap();
+        let (device, queue) = (Arc::new(device), Arc::new(queue));
+
+        for format in [TextureFormat::Rgba8UnormSrgb, TextureFormat::Bgra8UnormSrgb] {
+            let app = QuirkyApp::new(
+                device.clone(),
+                queue.clone(),
+                format,
+                |_, _, _| {},
+                |_| TestWidget::new(0.0, 0.0, vec![]) as Arc<dyn Widget>,
+            );
+            app.load_behaviour
+                .set(LoadBehaviour::Clear([1.0, 0.0, 0.0, 1.0]));
+
+            let image = app.render_to_image(uvec2(8, 4)).unwrap();
+
+            assert_eq!(image.dimensions(), (8, 4), "{format:?}");
+            assert_eq!(*image.get_pixel(0, 0), Rgba([255, 0, 0, 255]), "{format:?}");
+            assert_eq!(*image.get_pixel(7, 3), Rgba([255, 0, 0, 255]), "{format:?}");
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use crate::batching::InstanceBatcher;
+use crate::drawable_primitive::DrawablePrimitive;
+use crate::render_contexts::RenderContext;
+use crate::retained::{create_msaa_view, resolving_attachment};
+use glam::UVec2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use wgpu::util::DeviceExt;
+use wgpu::{
+    include_wgsl, BindGroup, BindGroupLayout, Buffer, Device, PipelineLayout, Queue, RenderPass,
+    RenderPipeline, ShaderModule, TextureFormat, TextureView,
+};
+
+/// How an opacity group is combined with what is drawn below it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Alpha blending over what is below
+    #[default]
+    Normal,
+    /// Adds the group's colors to what is below
+    Additive,
+    /// Multiplies what is below by the group's colors, for an opaque background
+    Multiply,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 3] = [BlendMode::Normal, BlendMode::Additive, BlendMode::Multiply];
+
+    /// The blend state for compositing premultiplied colors
+    fn blend_state(&self) -> wgpu::BlendState {
+        let over = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        match self {
+            BlendMode::Normal => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: over,
+            },
+            BlendMode::Multiply => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Dst,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: over,
+            },
+        }
+    }
+}
+
+/// Draws a widget subtree into an intermediate texture, which is then composited with a group
+/// opacity and blend mode, see [`crate::widget::Widget::opacity_group`]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OpacityGroup {
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+}
+
+#[derive(bytemuck::Zeroable, bytemuck::Pod, Copy, Clone)]
+#[repr(C)]
+struct GroupUniform {
+    opacity: f32,
+    _padding: [f32; 3],
+}
+
+pub(crate) struct GroupTarget {
+    view: TextureView,
+    msaa_view: Option<TextureView>,
+    pub batcher: InstanceBatcher,
+    uniform_buffer: Buffer,
+    bind_group: Arc<BindGroup>,
+    size: UVec2,
+}
+
+impl GroupTarget {
+    /// The attachment for drawing the group, clearing it to transparency
+    pub fn color_attachment(&self) -> wgpu::RenderPassColorAttachment {
+        resolving_attachment(
+            &self.view,
+            self.msaa_view.as_ref(),
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+        )
+    }
+}
+
+/// The intermediate textures of the opacity groups, cached across frames by group widget.
+///
+/// Group textures span the whole viewport, so that groups draw with the same camera and scissor
+/// rects as the rest of the frame.
+pub(crate) struct OpacityGroups {
+    shader: ShaderModule,
+    pipeline_layout: PipelineLayout,
+    pipelines: HashMap<BlendMode, Arc<RenderPipeline>>,
+    bind_group_layout: BindGroupLayout,
+    format: TextureFormat,
+    sample_count: u32,
+    targets: HashMap<Uuid, GroupTarget>,
+}
+
+impl OpacityGroups {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(include_wgsl!("shaders/opacity_group.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("opacity group"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("opacity group"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines = create_pipelines(device, &shader, &pipeline_layout, format, 1);
+
+        Self {
+            shader,
+            pipeline_layout,
+            pipelines,
+            bind_group_layout,
+            format,
+            sample_count: 1,
+            targets: Default::default(),
+        }
+    }
+
+    /// Makes sure every group of the frame has a texture of `size` holding its opacity,
+    /// dropping the textures of groups that are gone.
+    ///
+    /// Groups are drawn and composited with `sample_count` samples per pixel.
+    pub fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        size: UVec2,
+        sample_count: u32,
+        groups: impl Iterator<Item = (Uuid, OpacityGroup)>,
+    ) {
+        let size = size.max(UVec2::ONE);
+
+        if sample_count != self.sample_count {
+            self.pipelines = create_pipelines(
+                device,
+                &self.shader,
+                &self.pipeline_layout,
+                self.format,
+                sample_count,
+            );
+            self.sample_count = sample_count;
+            self.targets.clear();
+        }
+
+        let mut targets = HashMap::with_capacity(self.targets.len());
+
+        for (widget_id, group) in groups {
+            let target = match self.targets.remove(&widget_id) {
+                Some(target) if target.size == size => target,
+                _ => self.create_target(device, size),
+            };
+
+            queue.write_buffer(
+                &target.uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[GroupUniform {
+                    opacity: group.opacity.clamp(0.0, 1.0),
+                    _padding: [0.0; 3],
+                }]),
+            );
+
+            targets.insert(widget_id, target);
+        }
+
+        self.targets = targets;
+    }
+
+    fn create_target(&self, device: &Device, size: UVec2) -> GroupTarget {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("opacity group"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&Default::default());
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("opacity group"),
+            contents: bytemuck::cast_slice(&[GroupUniform {
+                opacity: 1.0,
+                _padding: [0.0; 3],
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("opacity group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        }));
+
+        GroupTarget {
+            msaa_view: create_msaa_view(device, size, self.format, self.sample_count),
+            view,
+            batcher: Default::default(),
+            uniform_buffer,
+            bind_group,
+            size,
+        }
+    }
+
+    /// The texture of a group prepared for this frame
+    pub fn target(&self, widget_id: Uuid) -> &GroupTarget {
+        self.targets
+            .get(&widget_id)
+            .expect("opacity group was not prepared")
+    }
+
+    pub fn target_mut(&mut self, widget_id: Uuid) -> &mut GroupTarget {
+        self.targets
+            .get_mut(&widget_id)
+            .expect("opacity group was not prepared")
+    }
+
+    /// A primitive compositing a prepared group where it is drawn
+    pub fn composite(&self, widget_id: Uuid, blend_mode: BlendMode) -> GroupComposite {
+        GroupComposite {
+            pipeline: self.pipelines[&blend_mode].clone(),
+            bind_group: self.target(widget_id).bind_group.clone(),
+        }
+    }
+}
+
+fn create_pipelines(
+    device: &Device,
+    shader: &ShaderModule,
+    pipeline_layout: &PipelineLayout,
+    format: TextureFormat,
+    sample_count: u32,
+) -> HashMap<BlendMode, Arc<RenderPipeline>> {
+    BlendMode::ALL
+        .into_iter()
+        .map(|blend_mode| {
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("opacity group composite"),
+                layout: Some(pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vs_fullscreen",
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: "fs_composite",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend_mode.blend_state()),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            });
+
+            (blend_mode, Arc::new(pipeline))
+        })
+        .collect()
+}
+
+/// Composites the texture of an opacity group, restricted to the group by its scissor rect
+pub(crate) struct GroupComposite {
+    pipeline: Arc<RenderPipeline>,
+    bind_group: Arc<BindGroup>,
+}
+
+impl DrawablePrimitive for GroupComposite {
+    fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, _render_context: &'a RenderContext<'a>) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,229 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use wgpu::{BindGroupLayout, Device, RenderPipeline, TextureFormat};
+
+/// What a pipeline draws into, pipelines being only usable with targets matching it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineTarget {
+    pub format: TextureFormat,
+    pub sample_count: u32,
+}
+
+/// A pipeline of a [`PipelineKind`], created for a [`PipelineTarget`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineId {
+    kind: TypeId,
+    target: PipelineTarget,
+}
+
+impl PipelineId {
+    pub fn of<P: PipelineKind>(target: PipelineTarget) -> Self {
+        Self {
+            kind: TypeId::of::<P>(),
+            target,
+        }
+    }
+}
+
+/// Everything a [`PipelineKind`] is created from
+pub struct PipelineContext<'a> {
+    pub device: &'a Device,
+    pub target: PipelineTarget,
+    pub camera_bind_group_layout: &'a BindGroupLayout,
+    /// Compiles the shaders embedded at build time rather than the files they were embedded from,
+    /// see [`crate::shader_source::ShaderSource`]
+    #[cfg(feature = "shader-hot-reload")]
+    pub(crate) embedded_shaders: bool,
+}
+
+impl<'a> PipelineContext<'a> {
+    pub fn new(
+        device: &'a Device,
+        target: PipelineTarget,
+        camera_bind_group_layout: &'a BindGroupLayout,
+    ) -> Self {
+        Self {
+            device,
+            target,
+            camera_bind_group_layout,
+            #[cfg(feature = "shader-hot-reload")]
+            embedded_shaders: false,
+        }
+    }
+}
+
+/// A render pipeline primitives draw with, identified by the implementing type.
+///
+/// The registry creates it once per target, so that `create` should take its color target
+/// format and multisample count from [`PipelineContext::target`].
+pub trait PipelineKind: 'static {
+    fn create(ctx: &PipelineContext) -> RenderPipeline;
+}
+
+type CreatePipeline = fn(&PipelineContext) -> RenderPipeline;
+
+/// The render pipelines of the primitives, keyed by kind and target.
+///
+/// Kinds are registered ahead of time with [`PipelineRegistry::register`], or when first used,
+/// and [`PipelineRegistry::warm_up`] creates all of them for a target before anything draws.
+#[derive(Default)]
+pub struct PipelineRegistry {
+    kinds: HashMap<TypeId, CreatePipeline>,
+    pipelines: HashMap<PipelineId, RenderPipeline>,
+}
+
+impl PipelineRegistry {
+    pub fn register<P: PipelineKind>(&mut self) {
+        self.kinds.insert(TypeId::of::<P>(), P::create);
+    }
+
+    /// Moves the registered kinds of `other` into this registry
+    pub fn register_all(&mut self, other: PipelineRegistry) {
+        self.kinds.extend(other.kinds);
+    }
+
+    /// Creates the pipelines of every registered kind for `ctx.target`, returning how many were
+    /// missing.
+    pub fn warm_up(&mut self, ctx: &PipelineContext) -> usize {
+        let mut created = 0;
+
+        for (kind, create) in self.kinds.iter() {
+            let id = PipelineId {
+                kind: *kind,
+                target: ctx.target,
+            };
+
+            self.pipelines.entry(id).or_insert_with(|| {
+                created += 1;
+                create_pipeline(*create, ctx)
+            });
+        }
+
+        created
+    }
+
+    /// The pipeline of kind `P` for `ctx.target`, registering the kind and creating the pipeline
+    /// if needed
+    pub fn get_or_create<P: PipelineKind>(&mut self, ctx: &PipelineContext) -> PipelineId {
+        self.register::<P>();
+
+        let id = PipelineId::of::<P>(ctx.target);
+        self.pipelines
+            .entry(id)
+            .or_insert_with(|| create_pipeline(P::create, ctx));
+
+        id
+    }
+
+    pub fn get(&self, id: &PipelineId) -> Option<&RenderPipeline> {
+        self.pipelines.get(id)
+    }
+
+    /// Recreates every pipeline from the current shader files, keeping the previous pipeline of a
+    /// kind whose shaders fail to compile.
+    ///
+    /// Ids stay the same, so primitives prepared before draw with the reloaded pipelines.
+    #[cfg(feature = "shader-hot-reload")]
+    pub fn reload(&mut self, device: &Device, camera_bind_group_layout: &BindGroupLayout) {
+        for (id, pipeline) in self.pipelines.iter_mut() {
+            let ctx = PipelineContext::new(device, id.target, camera_bind_group_layout);
+
+            match create_checked(self.kinds[&id.kind], &ctx) {
+                Ok(reloaded) => *pipeline = reloaded,
+                Err(error) => {
+                    tracing::error!(
+                        "failed to reload a pipeline, keeping the previous one: {error}"
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Creates a pipeline, from the embedded shaders if the shader files fail to compile
+#[cfg(feature = "shader-hot-reload")]
+fn create_pipeline(create: CreatePipeline, ctx: &PipelineContext) -> RenderPipeline {
+    create_checked(create, ctx).unwrap_or_else(|error| {
+        tracing::error!("failed to create a pipeline, using the embedded shaders: {error}");
+
+        create(&PipelineContext {
+            embedded_shaders: true,
+            ..*ctx
+        })
+    })
+}
+
+#[cfg(not(feature = "shader-hot-reload"))]
+fn create_pipeline(create: CreatePipeline, ctx: &PipelineContext) -> RenderPipeline {
+    create(ctx)
+}
+
+/// Creates a pipeline, capturing the validation errors that would otherwise be fatal
+#[cfg(feature = "shader-hot-reload")]
+fn create_checked(
+    create: CreatePipeline,
+    ctx: &PipelineContext,
+) -> Result<RenderPipeline, wgpu::Error> {
+    ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let pipeline = create(ctx);
+
+    match futures::executor::block_on(ctx.device.pop_error_scope()) {
+        Some(error) => Err(error),
+        None => Ok(pipeline),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::pipeline_registry::{PipelineContext, PipelineId, PipelineKind, PipelineTarget};
+    use wgpu::{RenderPipeline, TextureFormat};
+
+    /// Pipeline kinds that only serve as distinct ids, never created
+    pub(crate) struct PipelineA;
+    pub(crate) struct PipelineB;
+
+    impl PipelineKind for PipelineA {
+        fn create(_ctx: &PipelineContext) -> RenderPipeline {
+            unreachable!()
+        }
+    }
+
+    impl PipelineKind for PipelineB {
+        fn create(_ctx: &PipelineContext) -> RenderPipeline {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn pipeline_ids_differ_by_kind_and_target() {
+        let target = PipelineTarget {
+            format: TextureFormat::Bgra8UnormSrgb,
+            sample_count: 1,
+        };
+        let multisampled = PipelineTarget {
+            sample_count: 4,
+            ..target
+        };
+        let other_format = PipelineTarget {
+            format: TextureFormat::Rgba8UnormSrgb,
+            ..target
+        };
+
+        assert_eq!(
+            PipelineId::of::<PipelineA>(target),
+            PipelineId::of::<PipelineA>(target)
+        );
+        assert_ne!(
+            PipelineId::of::<PipelineA>(target),
+            PipelineId::of::<PipelineB>(target)
+        );
+        assert_ne!(
+            PipelineId::of::<PipelineA>(target),
+            PipelineId::of::<PipelineA>(multisampled)
+        );
+        assert_ne!(
+            PipelineId::of::<PipelineA>(target),
+            PipelineId::of::<PipelineA>(other_format)
+        );
+    }
+}
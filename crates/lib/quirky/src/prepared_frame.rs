@@ -0,0 +1,87 @@
+use crate::debug_overlay::PreparedOverlay;
+use crate::retained::RetainedBlit;
+use serde::{Deserialize, Serialize};
+use wgpu::{BindGroup, RenderPass};
+
+/// What the target holds before the ui is composited onto it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LoadBehaviour {
+    /// Clears the target to a color, given as linear rgba
+    Clear([f64; 4]),
+    /// Clears the target to transparency, for compositing the target elsewhere
+    Transparent,
+    /// Keeps what the target already holds, for drawing over an existing scene
+    Load,
+}
+
+impl Default for LoadBehaviour {
+    fn default() -> Self {
+        LoadBehaviour::Clear([0.0, 0.0, 0.0, 1.0])
+    }
+}
+
+impl LoadBehaviour {
+    pub fn load_op(&self) -> wgpu::LoadOp<wgpu::Color> {
+        match *self {
+            LoadBehaviour::Clear([r, g, b, a]) => wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a }),
+            LoadBehaviour::Transparent => wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            LoadBehaviour::Load => wgpu::LoadOp::Load,
+        }
+    }
+}
+
+/// A frame whose gpu work is already submitted, waiting to be composited onto a target.
+///
+/// See [`crate::QuirkyApp::prepare_frame`].
+pub struct PreparedFrame<'a> {
+    pub(crate) blit: RetainedBlit,
+    pub(crate) overlay: Option<PreparedOverlay>,
+    pub(crate) camera_bind_group: &'a BindGroup,
+    pub(crate) load_behaviour: LoadBehaviour,
+}
+
+impl<'a> PreparedFrame<'a> {
+    /// The load operation for a pass drawing the frame, according to the app's [`LoadBehaviour`]
+    pub fn load_op(&self) -> wgpu::LoadOp<wgpu::Color> {
+        self.load_behaviour.load_op()
+    }
+
+    /// Composites the ui onto the target of a pass owned by the host application.
+    ///
+    /// The ui is alpha blended over whatever the pass already drew, and the pass's pipeline,
+    /// bind groups and vertex buffers are left changed.
+    pub fn draw_into_pass(&'a self, pass: &mut RenderPass<'a>) {
+        self.blit.draw(pass);
+
+        if let Some(overlay) = self.overlay.as_ref() {
+            overlay.draw(pass, self.camera_bind_group);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prepared_frame::LoadBehaviour;
+
+    #[test]
+    fn load_behaviours_map_to_load_ops() {
+        assert_eq!(
+            LoadBehaviour::Clear([0.1, 0.2, 0.3, 0.4]).load_op(),
+            wgpu::LoadOp::Clear(wgpu::Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 0.4
+            })
+        );
+        assert_eq!(
+            LoadBehaviour::Transparent.load_op(),
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)
+        );
+        assert_eq!(LoadBehaviour::Load.load_op(), wgpu::LoadOp::Load);
+        assert_eq!(
+            LoadBehaviour::default().load_op(),
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+        );
+    }
+}
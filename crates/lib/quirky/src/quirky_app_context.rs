@@ -1,25 +1,60 @@
-use crate::widgets::events::{FocusState, MouseEvent, WidgetEvent};
+use crate::layers::{Layer, Layers};
+use crate::pipeline_registry::{PipelineKind, PipelineRegistry};
+use crate::widget::Widget;
+use crate::widgets::events::{EventDispatch, FocusState, MouseEvent, WidgetEvent};
 use async_std::channel::Sender;
 use async_std::prelude::Stream;
 use futures::channel::mpsc::channel;
-use futures_signals::signal::ReadOnlyMutable;
-use glam::UVec2;
+use futures_signals::signal::{Mutable, ReadOnlyMutable};
+use glam::{UVec2, Vec2};
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
-use wgpu::{Device, Queue};
 
 #[derive(Default)]
 pub struct QuirkyResources {
     resources: HashMap<TypeId, Box<dyn Any + Send>>,
+    shared: SharedResources,
+    /// Kinds registered before the app is set up, moved into its registry to be warmed up
+    pub(crate) pipeline_kinds: PipelineRegistry,
+    #[cfg(feature = "shader-hot-reload")]
+    pub(crate) shader_dirs: Vec<std::path::PathBuf>,
 }
 
 impl QuirkyResources {
+    /// Registers a pipeline kind to be created when the app is set up, rather than by the first
+    /// primitive drawing with it
+    pub fn register_pipeline<P: PipelineKind>(&mut self) {
+        self.pipeline_kinds.register::<P>();
+    }
+
+    /// Reloads the pipelines when a WGSL file in `dir` changes, see
+    /// [`crate::shader_source::ShaderSource`]
+    #[cfg(feature = "shader-hot-reload")]
+    pub fn watch_shaders(&mut self, dir: impl Into<std::path::PathBuf>) {
+        self.shader_dirs.push(dir.into());
+    }
+
+    /// Inserts a resource widgets can read while preparing in parallel, see [`SharedResources`]
+    pub fn insert_shared<T: Send + Sync + 'static>(&mut self, resource: T) {
+        self.shared
+            .resources
+            .insert(TypeId::of::<T>(), Arc::new(resource));
+    }
+
+    pub fn shared(&self) -> &SharedResources {
+        &self.shared
+    }
+
     pub fn insert<T: Send + 'static>(&mut self, resource: T) {
         self.resources.insert(TypeId::of::<T>(), Box::new(resource));
     }
 
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
     pub fn get_resource<T: 'static>(&self) -> anyhow::Result<&T> {
         let type_id = TypeId::of::<T>();
         let resource = self
@@ -44,40 +79,93 @@ impl QuirkyResources {
     }
 }
 
+/// Resources read from several threads at once by
+/// [`crate::widget::Widget::prepare_parallel`], so that any state they mutate needs its own
+/// synchronization.
+#[derive(Default)]
+pub struct SharedResources {
+    resources: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl SharedResources {
+    pub fn get_resource<T: 'static>(&self) -> anyhow::Result<&T> {
+        let type_id = TypeId::of::<T>();
+        let resource = self
+            .resources
+            .get(&type_id)
+            .ok_or_else(|| anyhow::anyhow!("Shared resource {:?} not found", type_id))?;
+
+        resource
+            .downcast_ref::<T>()
+            .ok_or_else(|| anyhow::anyhow!("Resource type mismatch"))
+    }
+}
+
+/// The reactive core shared by all widgets: event dispatch, focus, resources and the viewport.
+///
+/// It holds no graphics state, so widget run loops can be driven without a device.
 pub struct QuirkyAppContext {
-    pub device: Arc<Device>,
-    pub queue: Arc<Queue>,
+    /// The size of the target in physical pixels
     pub viewport_size: ReadOnlyMutable<UVec2>,
+    /// Physical pixels per logical unit, see [`QuirkyAppContext::logical_viewport_size`]
+    pub scale_factor: ReadOnlyMutable<f64>,
     pub resources: Arc<Mutex<QuirkyResources>>,
     signal_dirty: Sender<()>,
     widget_event_subscriptions:
         std::sync::Mutex<HashMap<Uuid, futures::channel::mpsc::Sender<WidgetEvent>>>,
     focused_widget_id: std::sync::Mutex<Option<Uuid>>,
+    event_taps: std::sync::Mutex<Vec<futures::channel::mpsc::Sender<EventDispatch>>>,
+    pub layers: Layers,
 }
 
 impl QuirkyAppContext {
     pub fn new(
-        device: Arc<Device>,
-        queue: Arc<Queue>,
         viewport_size: ReadOnlyMutable<UVec2>,
         signal_dirty: Sender<()>,
         resources: Arc<Mutex<QuirkyResources>>,
     ) -> Self {
         Self {
-            device: device.into(),
-            queue: queue.into(),
             widget_event_subscriptions: Default::default(),
             viewport_size,
+            scale_factor: Mutable::new(1.0).read_only(),
             resources,
             signal_dirty,
             focused_widget_id: Default::default(),
+            event_taps: Default::default(),
+            layers: Default::default(),
         }
     }
 
+    /// Follows `scale_factor` instead of the default of one physical pixel per logical unit
+    pub fn with_scale_factor(mut self, scale_factor: ReadOnlyMutable<f64>) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
+    /// The size of the viewport in the logical units widgets are laid out in
+    pub fn logical_viewport_size(&self) -> Vec2 {
+        crate::logical_size(self.viewport_size.get(), self.scale_factor.get())
+    }
+
     pub async fn signal_redraw(&self) {
         self.signal_dirty.send(()).await.unwrap();
     }
 
+    /// Registers a subtree to be drawn, run and hit tested in `layer`, above the ones already there.
+    ///
+    /// The subtree is not laid out by anything, its bounding box is up to the caller.
+    pub fn add_to_layer(&self, layer: Layer, widget: Arc<dyn Widget>) {
+        self.layers.add(layer, widget);
+        let _ = self.signal_dirty.try_send(());
+    }
+
+    pub fn remove_from_layer(&self, layer: Layer, widget_id: Uuid) -> bool {
+        let removed = self.layers.remove(layer, widget_id);
+        let _ = self.signal_dirty.try_send(());
+
+        removed
+    }
+
     pub fn dispatch_event(&self, mut target: Uuid, event: WidgetEvent) -> anyhow::Result<()> {
         let mut sender_lock = self.widget_event_subscriptions.lock().unwrap();
 
@@ -105,6 +193,8 @@ impl QuirkyAppContext {
             }
         };
 
+        self.tap_event(target, &event);
+
         if let Some(sender) = sender_lock.get_mut(&target) {
             if sender.is_closed() {
                 sender_lock.remove(&target);
@@ -116,6 +206,30 @@ impl QuirkyAppContext {
         Ok(())
     }
 
+    /// Receives a copy of every event dispatched from now on, after focus rerouting.
+    ///
+    /// Events are dropped for taps that fall behind.
+    pub fn tap_events(&self) -> impl Stream<Item = EventDispatch> + Unpin + Send {
+        let (tx, rx) = channel(1000);
+
+        self.event_taps.lock().unwrap().push(tx);
+
+        rx
+    }
+
+    fn tap_event(&self, target: Uuid, event: &WidgetEvent) {
+        let mut taps = self.event_taps.lock().unwrap();
+
+        taps.retain(|tap| !tap.is_closed());
+
+        for tap in taps.iter_mut() {
+            let _ = tap.try_send(EventDispatch {
+                receiver_id: target,
+                event: event.clone(),
+            });
+        }
+    }
+
     pub async fn subscribe_to_widget_events(
         &self,
         event_receiver: Uuid,
@@ -137,3 +251,92 @@ impl QuirkyAppContext {
         let _ = self.dispatch_event(widget_id, WidgetEvent::FocusChange(FocusState::Focused));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::quirky_app_context::QuirkyAppContext;
+    use crate::widgets::events::{
+        FocusState, KeyCode, KeyboardEvent, MouseButton, MouseEvent, WidgetEvent,
+    };
+    use futures::StreamExt;
+    use futures_signals::signal::Mutable;
+    use glam::UVec2;
+    use uuid::Uuid;
+
+    fn key_pressed(key_code: KeyCode) -> WidgetEvent {
+        WidgetEvent::KeyboardEvent {
+            event: KeyboardEvent::KeyPressed {
+                key_code,
+                modifier: Default::default(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn keyboard_events_follow_focus() {
+        let (tx, _rx) = async_std::channel::unbounded();
+        let ctx = QuirkyAppContext::new(
+            Mutable::new(UVec2::new(100, 100)).read_only(),
+            tx,
+            Default::default(),
+        );
+
+        let (focused_id, other_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut focused = Box::pin(ctx.subscribe_to_widget_events(focused_id).await);
+        let mut other = Box::pin(ctx.subscribe_to_widget_events(other_id).await);
+
+        ctx.request_focus(focused_id);
+
+        assert!(matches!(
+            focused.next().await,
+            Some(WidgetEvent::FocusChange(FocusState::Focused))
+        ));
+
+        ctx.dispatch_event(other_id, key_pressed(KeyCode::A))
+            .unwrap();
+
+        assert!(matches!(
+            focused.next().await,
+            Some(WidgetEvent::KeyboardEvent {
+                event: KeyboardEvent::KeyPressed {
+                    key_code: KeyCode::A,
+                    ..
+                }
+            })
+        ));
+
+        ctx.dispatch_event(
+            other_id,
+            WidgetEvent::MouseEvent {
+                event: MouseEvent::ButtonDown {
+                    button: MouseButton::Left,
+                },
+            },
+        )
+        .unwrap();
+
+        assert!(matches!(
+            focused.next().await,
+            Some(WidgetEvent::FocusChange(FocusState::Unfocused))
+        ));
+        assert!(matches!(
+            other.next().await,
+            Some(WidgetEvent::MouseEvent {
+                event: MouseEvent::ButtonDown { .. }
+            })
+        ));
+
+        ctx.dispatch_event(other_id, key_pressed(KeyCode::B))
+            .unwrap();
+
+        assert!(matches!(
+            other.next().await,
+            Some(WidgetEvent::KeyboardEvent {
+                event: KeyboardEvent::KeyPressed {
+                    key_code: KeyCode::B,
+                    ..
+                }
+            })
+        ));
+    }
+}
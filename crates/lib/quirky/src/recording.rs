@@ -0,0 +1,292 @@
+use crate::input_driver::InputTarget;
+use crate::widgets::events::{MouseEvent, WidgetEvent};
+use async_std::task::sleep;
+use glam::UVec2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Milliseconds since the recording started
+    pub timestamp_ms: u64,
+    /// The widget the event was dispatched to, as identified in the recorded app
+    pub target: Uuid,
+    /// The last known pointer position when the event was dispatched, in logical units
+    pub pointer_pos: Option<UVec2>,
+    pub event: WidgetEvent,
+}
+
+/// A session of input events, as dispatched to the widgets of an app.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InputRecording {
+    /// The size of the viewport in physical pixels
+    pub viewport_size: UVec2,
+    /// Physical pixels per logical unit of the recorded positions
+    pub scale_factor: f64,
+    pub events: Vec<RecordedEvent>,
+}
+
+impl InputRecording {
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+pub(crate) struct Recorder {
+    started: Instant,
+    pointer_pos: Option<UVec2>,
+    recording: InputRecording,
+}
+
+impl Recorder {
+    pub(crate) fn new(viewport_size: UVec2, scale_factor: f64) -> Self {
+        Self {
+            started: Instant::now(),
+            pointer_pos: None,
+            recording: InputRecording {
+                viewport_size,
+                scale_factor,
+                events: vec![],
+            },
+        }
+    }
+
+    pub(crate) fn record(&mut self, target: Uuid, event: &WidgetEvent) {
+        if let WidgetEvent::MouseEvent { event } = event {
+            match event {
+                MouseEvent::Enter { pos } | MouseEvent::Move { pos } => {
+                    self.pointer_pos = Some(*pos)
+                }
+                MouseEvent::Drag { to, .. } => self.pointer_pos = Some(*to),
+                _ => {}
+            }
+        }
+
+        self.recording.events.push(RecordedEvent {
+            timestamp_ms: self.started.elapsed().as_millis() as u64,
+            target,
+            pointer_pos: self.pointer_pos,
+            event: event.clone(),
+        });
+    }
+
+    pub(crate) fn finish(self) -> InputRecording {
+        self.recording
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayPacing {
+    /// Waits between events as long as the recorded session did
+    Recorded,
+    /// Dispatches all events back to back
+    Immediate,
+}
+
+/// Dispatches a recorded session to `target`.
+///
+/// Widget ids differ between runs, so recorded targets are mapped to the widget found at the
+/// recorded pointer position by every enter, move and button down event, and the other events go
+/// to the widget their target was last mapped to. Events for widgets that were never hit are
+/// dropped, except keyboard events sent to the nil id, which are left for the context to route to
+/// the focused widget.
+///
+/// Recorded positions are scaled to the target's [`InputTarget::scale_factor`], so they point at
+/// the same physical pixels. The widget tree should be laid out at the recorded viewport size
+/// before replaying.
+pub async fn replay(target: &impl InputTarget, recording: &InputRecording, pacing: ReplayPacing) {
+    let mut widget_ids: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut elapsed_ms = 0;
+    let scale = recording.scale_factor / target.scale_factor();
+
+    for recorded in recording.events.iter() {
+        if pacing == ReplayPacing::Recorded && recorded.timestamp_ms > elapsed_ms {
+            sleep(Duration::from_millis(recorded.timestamp_ms - elapsed_ms)).await;
+            elapsed_ms = recorded.timestamp_ms;
+        }
+
+        let hits_pointer_target = matches!(
+            recorded.event,
+            WidgetEvent::MouseEvent {
+                event: MouseEvent::Enter { .. }
+                    | MouseEvent::Move { .. }
+                    | MouseEvent::ButtonDown { .. }
+            }
+        );
+
+        if hits_pointer_target {
+            if let Some(hit) = recorded
+                .pointer_pos
+                .and_then(|pos| target.get_widgets_at(rescale(pos, scale)))
+                .and_then(|widgets| widgets.first().copied())
+            {
+                widget_ids.insert(recorded.target, hit);
+            }
+        }
+
+        let widget_id = if recorded.target.is_nil() {
+            Some(Uuid::nil())
+        } else {
+            widget_ids.get(&recorded.target).copied()
+        };
+
+        if let Some(widget_id) = widget_id {
+            target.dispatch_event_to_widget(widget_id, rescale_event(&recorded.event, scale));
+        }
+    }
+}
+
+fn rescale(pos: UVec2, scale: f64) -> UVec2 {
+    (pos.as_dvec2() * scale).round().as_uvec2()
+}
+
+fn rescale_event(event: &WidgetEvent, scale: f64) -> WidgetEvent {
+    let WidgetEvent::MouseEvent { event } = event else {
+        return event.clone();
+    };
+
+    let event = match event {
+        MouseEvent::Enter { pos } => MouseEvent::Enter {
+            pos: rescale(*pos, scale),
+        },
+        MouseEvent::Move { pos } => MouseEvent::Move {
+            pos: rescale(*pos, scale),
+        },
+        MouseEvent::Drag { from, to, button } => MouseEvent::Drag {
+            from: rescale(*from, scale),
+            to: rescale(*to, scale),
+            button: *button,
+        },
+        event => event.clone(),
+    };
+
+    WidgetEvent::MouseEvent { event }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::input_driver::InputTarget;
+    use crate::recording::{replay, InputRecording, Recorder, ReplayPacing};
+    use crate::widgets::events::{MouseButton, MouseEvent, WidgetEvent};
+    use glam::{uvec2, UVec2};
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    struct FakeTarget {
+        widget_id: Uuid,
+        scale_factor: f64,
+        hit_positions: Mutex<Vec<UVec2>>,
+        dispatched: Mutex<Vec<Uuid>>,
+    }
+
+    impl FakeTarget {
+        fn new(scale_factor: f64) -> Self {
+            Self {
+                widget_id: Uuid::new_v4(),
+                scale_factor,
+                hit_positions: Default::default(),
+                dispatched: Default::default(),
+            }
+        }
+    }
+
+    impl InputTarget for FakeTarget {
+        fn get_widgets_at(&self, pos: UVec2) -> Option<Vec<Uuid>> {
+            self.hit_positions.lock().unwrap().push(pos);
+            (pos.x < 50).then(|| vec![self.widget_id])
+        }
+
+        fn dispatch_event_to_widget(&self, target: Uuid, _event: WidgetEvent) {
+            self.dispatched.lock().unwrap().push(target);
+        }
+
+        fn scale_factor(&self) -> f64 {
+            self.scale_factor
+        }
+    }
+
+    fn mouse(event: MouseEvent) -> WidgetEvent {
+        WidgetEvent::MouseEvent { event }
+    }
+
+    #[tokio::test]
+    async fn recordings_roundtrip_and_replay_onto_new_widget_ids() {
+        let (recorded_id, missed_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut recorder = Recorder::new(uvec2(100, 100), 1.0);
+
+        recorder.record(missed_id, &mouse(MouseEvent::Move { pos: uvec2(80, 10) }));
+        recorder.record(recorded_id, &mouse(MouseEvent::Move { pos: uvec2(10, 10) }));
+        recorder.record(
+            recorded_id,
+            &mouse(MouseEvent::ButtonDown {
+                button: MouseButton::Left,
+            }),
+        );
+        recorder.record(
+            recorded_id,
+            &mouse(MouseEvent::ButtonUp {
+                button: MouseButton::Left,
+            }),
+        );
+
+        let json = serde_json::to_string(&recorder.finish()).unwrap();
+        let recording: InputRecording = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(recording.viewport_size, uvec2(100, 100));
+        assert_eq!(recording.scale_factor, 1.0);
+        assert_eq!(recording.events.len(), 4);
+        assert_eq!(recording.events[3].pointer_pos, Some(uvec2(10, 10)));
+
+        let target = FakeTarget::new(1.0);
+
+        replay(&target, &recording, ReplayPacing::Immediate).await;
+
+        assert_eq!(
+            *target.dispatched.lock().unwrap(),
+            vec![target.widget_id; 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_point_at_the_same_physical_pixels_at_another_scale() {
+        let recorded_id = Uuid::new_v4();
+        let mut recorder = Recorder::new(uvec2(200, 200), 2.0);
+
+        recorder.record(recorded_id, &mouse(MouseEvent::Move { pos: uvec2(30, 10) }));
+        recorder.record(
+            recorded_id,
+            &mouse(MouseEvent::ButtonDown {
+                button: MouseButton::Left,
+            }),
+        );
+
+        let recording = recorder.finish();
+
+        // 30 logical units at a scale of 2 are 60 pixels, which is 60 units at a scale of 1
+        let unscaled = FakeTarget::new(1.0);
+        replay(&unscaled, &recording, ReplayPacing::Immediate).await;
+
+        assert_eq!(
+            *unscaled.hit_positions.lock().unwrap(),
+            vec![uvec2(60, 20); 2]
+        );
+        assert!(unscaled.dispatched.lock().unwrap().is_empty());
+
+        let scaled = FakeTarget::new(4.0);
+        replay(&scaled, &recording, ReplayPacing::Immediate).await;
+
+        assert_eq!(*scaled.hit_positions.lock().unwrap(), vec![uvec2(15, 5); 2]);
+        assert_eq!(
+            *scaled.dispatched.lock().unwrap(),
+            vec![scaled.widget_id; 2]
+        );
+    }
+}
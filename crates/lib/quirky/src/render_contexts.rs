@@ -1,23 +1,82 @@
+use crate::gpu_pool::{GpuPool, PoolKey};
+use crate::pipeline_registry::{
+    PipelineContext, PipelineId, PipelineKind, PipelineRegistry, PipelineTarget,
+};
 use crate::quirky_app_context::QuirkyResources;
-use glam::UVec2;
+use glam::{Affine2, UVec2};
 use std::collections::HashMap;
 use uuid::Uuid;
 use wgpu::{BindGroup, BindGroupLayout, Device, Queue, RenderPipeline, TextureFormat};
 
 pub struct RenderContext<'a> {
-    pub resources: &'a mut QuirkyResources,
+    pub resources: &'a QuirkyResources,
+    /// The camera of the widget being drawn, including its transform
     pub camera_bind_group: &'a BindGroup,
     pub screen_resolution: UVec2,
-    pub pipeline_cache: &'a HashMap<Uuid, RenderPipeline>,
+    pub pipelines: &'a PipelineRegistry,
     pub bind_group_cache: &'a HashMap<Uuid, BindGroup>,
 }
 
+impl<'a> RenderContext<'a> {
+    /// A pipeline obtained with [`PrepareContext::pipeline`] while preparing this frame
+    pub fn pipeline(&self, id: PipelineId) -> &'a RenderPipeline {
+        self.pipelines
+            .get(&id)
+            .expect("pipeline was not created while preparing")
+    }
+}
+
 pub struct PrepareContext<'a> {
     pub resources: &'a mut QuirkyResources,
     pub device: &'a Device,
     pub queue: &'a Queue,
     pub surface_format: TextureFormat,
-    pub pipeline_cache: &'a mut HashMap<Uuid, RenderPipeline>,
+    /// The size of the target in physical pixels
+    pub screen_resolution: UVec2,
+    /// Physical pixels per logical unit, for primitives that draw in physical pixels
+    pub scale_factor: f64,
+    /// Samples per pixel of the targets primitives draw into, for the multisample state of their
+    /// pipelines
+    pub sample_count: u32,
+    pub pipelines: &'a mut PipelineRegistry,
     pub bind_group_cache: &'a mut HashMap<Uuid, BindGroup>,
     pub camera_bind_group_layout: &'a BindGroupLayout,
+    pub gpu_pool: &'a mut GpuPool,
+    /// The widget owning the primitive being prepared
+    pub widget_id: Uuid,
+    /// The position of the primitive being prepared among the primitives of its widget
+    pub primitive_index: usize,
+    /// Maps the logical space of the widget being prepared to the screen, for primitives not
+    /// drawn through the camera
+    pub transform: Affine2,
+}
+
+impl PrepareContext<'_> {
+    /// The key of a pooled allocation of the primitive being prepared
+    pub fn pool_key(&self, slot: &'static str) -> PoolKey {
+        PoolKey {
+            widget_id: self.widget_id,
+            primitive_index: self.primitive_index,
+            slot,
+        }
+    }
+
+    /// What the primitives of this frame draw into
+    pub fn pipeline_target(&self) -> PipelineTarget {
+        PipelineTarget {
+            format: self.surface_format,
+            sample_count: self.sample_count,
+        }
+    }
+
+    /// The pipeline of kind `P` for the target of this frame, created if it was not warmed up
+    pub fn pipeline<P: PipelineKind>(&mut self) -> PipelineId {
+        let target = self.pipeline_target();
+
+        self.pipelines.get_or_create::<P>(&PipelineContext::new(
+            self.device,
+            target,
+            self.camera_bind_group_layout,
+        ))
+    }
 }
@@ -0,0 +1,296 @@
+use crate::LayoutBox;
+use glam::UVec2;
+use std::sync::Arc;
+use wgpu::{
+    include_wgsl, BindGroup, BindGroupLayout, Device, PipelineLayout, PipelineLayoutDescriptor,
+    RenderPass, RenderPipeline, ShaderModule, TextureFormat, TextureView,
+};
+
+struct RetainedTexture {
+    view: TextureView,
+    /// The multisampled texture drawn into and resolved to `view`, when multisampling
+    msaa_view: Option<TextureView>,
+    blit_bind_group: Arc<BindGroup>,
+    size: UVec2,
+    scale_factor: f64,
+}
+
+/// An offscreen copy of the last frame, so that only damaged areas need to be redrawn.
+///
+/// Frames are drawn onto transparency in the retained texture, which is then composited onto
+/// the actual target with a fullscreen blit.
+pub(crate) struct RetainedFrame {
+    shader: ShaderModule,
+    fill_pipeline_layout: PipelineLayout,
+    fill_pipeline: RenderPipeline,
+    blit_pipeline: Arc<RenderPipeline>,
+    blit_bind_group_layout: BindGroupLayout,
+    format: TextureFormat,
+    sample_count: u32,
+    texture: Option<RetainedTexture>,
+}
+
+impl RetainedFrame {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(include_wgsl!("shaders/retained.wgsl"));
+
+        let fill_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("retained frame fill"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let blit_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("retained frame blit"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                }],
+            });
+
+        let blit_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("retained frame blit"),
+            bind_group_layouts: &[&blit_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            fill_pipeline: create_pipeline(
+                device,
+                &shader,
+                &fill_pipeline_layout,
+                "fs_fill",
+                format,
+                None,
+                1,
+            ),
+            blit_pipeline: Arc::new(create_pipeline(
+                device,
+                &shader,
+                &blit_pipeline_layout,
+                "fs_blit",
+                format,
+                Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                1,
+            )),
+            shader,
+            fill_pipeline_layout,
+            blit_bind_group_layout,
+            format,
+            sample_count: 1,
+            texture: None,
+        }
+    }
+
+    /// Makes sure the retained texture matches `size` and `sample_count`, returning true when its
+    /// contents were lost.
+    ///
+    /// A change of scale factor invalidates the contents as well, as everything drawn scales.
+    pub fn ensure_size(
+        &mut self,
+        device: &Device,
+        size: UVec2,
+        scale_factor: f64,
+        sample_count: u32,
+    ) -> bool {
+        let size = size.max(UVec2::ONE);
+
+        if sample_count != self.sample_count {
+            self.fill_pipeline = create_pipeline(
+                device,
+                &self.shader,
+                &self.fill_pipeline_layout,
+                "fs_fill",
+                self.format,
+                None,
+                sample_count,
+            );
+            self.sample_count = sample_count;
+            self.texture = None;
+        }
+
+        if let Some(texture) = self.texture.as_mut().filter(|t| t.size == size) {
+            let scale_changed = texture.scale_factor != scale_factor;
+            texture.scale_factor = scale_factor;
+
+            return scale_changed;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("retained frame"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&Default::default());
+
+        let blit_bind_group = Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("retained frame blit"),
+            layout: &self.blit_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        }));
+
+        self.texture = Some(RetainedTexture {
+            view,
+            msaa_view: create_msaa_view(device, size, self.format, sample_count),
+            blit_bind_group,
+            size,
+            scale_factor,
+        });
+
+        true
+    }
+
+    /// The samples per pixel the retained frame is drawn with
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// The attachment for drawing into the retained frame, resolving it when multisampling
+    pub fn color_attachment(&self) -> wgpu::RenderPassColorAttachment {
+        let texture = self.texture.as_ref().expect("retained frame is sized");
+
+        resolving_attachment(
+            &texture.view,
+            texture.msaa_view.as_ref(),
+            wgpu::LoadOp::Load,
+        )
+    }
+
+    /// Restricts drawing to `damage` and clears it to transparency
+    pub fn begin_damage<'a>(&'a self, pass: &mut RenderPass<'a>, damage: LayoutBox) {
+        let (x, y, width, height) = damage.scissor_rect();
+
+        pass.set_scissor_rect(x, y, width, height);
+        pass.set_pipeline(&self.fill_pipeline);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// What is needed to composite the retained frame onto a target, see [`RetainedBlit::draw`]
+    pub fn blit(&self) -> RetainedBlit {
+        let texture = self.texture.as_ref().expect("retained frame is sized");
+
+        RetainedBlit {
+            pipeline: self.blit_pipeline.clone(),
+            bind_group: texture.blit_bind_group.clone(),
+        }
+    }
+}
+
+pub(crate) struct RetainedBlit {
+    pipeline: Arc<RenderPipeline>,
+    bind_group: Arc<BindGroup>,
+}
+
+impl RetainedBlit {
+    pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// A multisampled texture to draw into before resolving to a texture of `size`, `None` when
+/// `sample_count` does not multisample
+pub(crate) fn create_msaa_view(
+    device: &Device,
+    size: UVec2,
+    format: TextureFormat,
+    sample_count: u32,
+) -> Option<TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("multisampled target"),
+        size: wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+
+    Some(texture.create_view(&Default::default()))
+}
+
+/// Draws into `msaa_view` resolving to `view` when multisampling, or into `view` directly.
+///
+/// The multisampled texture is stored as well, as damaged frames load what it held.
+pub(crate) fn resolving_attachment<'a>(
+    view: &'a TextureView,
+    msaa_view: Option<&'a TextureView>,
+    load: wgpu::LoadOp<wgpu::Color>,
+) -> wgpu::RenderPassColorAttachment<'a> {
+    wgpu::RenderPassColorAttachment {
+        view: msaa_view.unwrap_or(view),
+        resolve_target: msaa_view.map(|_| view),
+        ops: wgpu::Operations { load, store: true },
+    }
+}
+
+fn create_pipeline(
+    device: &Device,
+    shader: &ShaderModule,
+    pipeline_layout: &PipelineLayout,
+    fragment_entry_point: &str,
+    format: TextureFormat,
+    blend: Option<wgpu::BlendState>,
+    sample_count: u32,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("retained frame"),
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_fullscreen",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Polls the WGSL files of some directories for changes.
+///
+/// Modification times are compared rather than relying on file system events, which keeps the
+/// watcher portable and independent of the async runtime.
+pub(crate) struct ShaderWatcher {
+    dirs: Vec<PathBuf>,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderWatcher {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        let mut watcher = Self {
+            dirs,
+            modified: Default::default(),
+        };
+
+        watcher.poll();
+        watcher
+    }
+
+    /// Whether a shader was added, changed or removed since the last poll
+    pub fn poll(&mut self) -> bool {
+        let mut modified = HashMap::with_capacity(self.modified.len());
+
+        for dir in self.dirs.iter() {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+
+                if path.extension().and_then(|extension| extension.to_str()) != Some("wgsl") {
+                    continue;
+                }
+
+                if let Ok(time) = entry.metadata().and_then(|metadata| metadata.modified()) {
+                    modified.insert(path, time);
+                }
+            }
+        }
+
+        let changed = modified != self.modified;
+        self.modified = modified;
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::shader_hot_reload::ShaderWatcher;
+
+    #[test]
+    fn added_and_removed_shaders_are_changes() {
+        let dir = std::env::temp_dir().join(format!("quirky-shaders-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut watcher = ShaderWatcher::new(vec![dir.clone()]);
+        assert!(!watcher.poll());
+
+        std::fs::write(dir.join("notes.txt"), "not a shader").unwrap();
+        assert!(!watcher.poll());
+
+        std::fs::write(dir.join("quad.wgsl"), "").unwrap();
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+
+        std::fs::remove_file(dir.join("quad.wgsl")).unwrap();
+        assert!(watcher.poll());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
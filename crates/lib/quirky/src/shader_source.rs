@@ -0,0 +1,57 @@
+use crate::pipeline_registry::PipelineContext;
+use std::borrow::Cow;
+use wgpu::ShaderModule;
+
+/// WGSL embedded at build time, along with the path of the file it was embedded from.
+///
+/// With the `shader-hot-reload` feature, the file is compiled instead of the embedded source, so
+/// that [`crate::pipeline_registry::PipelineRegistry::reload`] picks up edits without a rebuild.
+/// Use [`crate::shader_source!`] to create one.
+#[derive(Clone, Copy, Debug)]
+pub struct ShaderSource {
+    pub path: &'static str,
+    pub embedded: &'static str,
+}
+
+impl ShaderSource {
+    pub fn create_module(&self, ctx: &PipelineContext) -> ShaderModule {
+        #[cfg(feature = "shader-hot-reload")]
+        let source = self.read(ctx.embedded_shaders);
+        #[cfg(not(feature = "shader-hot-reload"))]
+        let source = Cow::Borrowed(self.embedded);
+
+        ctx.device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(self.path),
+                source: wgpu::ShaderSource::Wgsl(source),
+            })
+    }
+
+    /// The file, or the embedded source if it cannot be read
+    #[cfg(feature = "shader-hot-reload")]
+    fn read(&self, embedded: bool) -> Cow<'static, str> {
+        if embedded {
+            return Cow::Borrowed(self.embedded);
+        }
+
+        match std::fs::read_to_string(self.path) {
+            Ok(source) => Cow::Owned(source),
+            Err(error) => {
+                tracing::warn!("failed to read shader {}: {error}", self.path);
+                Cow::Borrowed(self.embedded)
+            }
+        }
+    }
+}
+
+/// A [`ShaderSource`] for a WGSL file, the path being relative to the manifest of the calling
+/// crate
+#[macro_export]
+macro_rules! shader_source {
+    ($path:literal) => {
+        $crate::shader_source::ShaderSource {
+            path: concat!(env!("CARGO_MANIFEST_DIR"), "/", $path),
+            embedded: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+        }
+    };
+}
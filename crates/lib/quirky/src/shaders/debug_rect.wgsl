@@ -0,0 +1,43 @@
+struct UiCameraUniform {
+    transform: mat4x4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> camera: UiCameraUniform;
+
+struct RectInfo {
+    @location(0) pos: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) color: vec4<f32>
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, r: RectInfo) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0)
+    );
+
+    var out: VertexOutput;
+
+    let corner = corners[vertex_index];
+    let pos = corner * r.size + r.pos;
+
+    out.position = camera.transform * vec4<f32>(pos.x, pos.y, 0.0, 1.0);
+    out.color = r.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,33 @@
+// Padded to the 16 bytes uniform structs are required to span
+struct GroupUniform {
+    opacity: f32,
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+};
+
+@group(0) @binding(0)
+var group_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var<uniform> group: GroupUniform;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>
+};
+
+// A triangle covering the whole target, the composited area being set by the scissor rect
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+// Group textures are drawn onto transparency, so they hold premultiplied colors
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(group_texture, vec2<i32>(in.position.xy), 0) * group.opacity;
+}
@@ -0,0 +1,27 @@
+@group(0) @binding(0)
+var retained: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>
+};
+
+// A triangle covering the whole target
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_fill(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+}
+
+// The retained frame holds premultiplied colors, from alpha blending onto transparency
+@fragment
+fn fs_blit(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(retained, vec2<i32>(in.position.xy), 0);
+}
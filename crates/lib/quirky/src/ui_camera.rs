@@ -1,4 +1,4 @@
-use glam::{vec3, UVec2, Vec4};
+use glam::{vec3, Affine2, Mat4, UVec2, Vec4};
 
 #[derive(bytemuck::Zeroable, bytemuck::Pod, Copy, Clone)]
 #[repr(C)]
@@ -6,6 +6,7 @@ pub struct UiCameraUniform {
     pub transform: [[f32; 4]; 4],
 }
 
+/// Maps logical units to clip space, for a target of a given size in physical pixels
 #[derive(Default)]
 pub struct UiCamera2D {
     transform: glam::Mat4,
@@ -19,8 +20,23 @@ impl UiCamera2D {
         }
     }
 
-    pub fn resize_viewport(&mut self, size: UVec2) {
+    /// The camera uniform for widgets whose logical space is mapped by `transform` first
+    pub fn create_transformed_camera_uniform(&self, transform: Affine2) -> UiCameraUniform {
+        let transform = Mat4::from_cols(
+            transform.matrix2.x_axis.extend(0.0).extend(0.0),
+            transform.matrix2.y_axis.extend(0.0).extend(0.0),
+            Vec4::Z,
+            transform.translation.extend(0.0).extend(1.0),
+        );
+
+        UiCameraUniform {
+            transform: (self.transform * transform).to_cols_array_2d(),
+        }
+    }
+
+    pub fn resize_viewport(&mut self, size: UVec2, scale_factor: f64) {
         self.screen_resolution = size;
+        let scale_factor = scale_factor as f32;
         let camera_origin_translate = glam::Mat4::from_translation(vec3(-1.0, 1.0, 0.0));
 
         let camera_coordinate_transform = glam::mat4(
@@ -31,8 +47,8 @@ impl UiCamera2D {
         );
 
         let ndc_scale = glam::mat4(
-            Vec4::new(2.0 / size.x as f32, 0.0, 0.0, 0.0),
-            Vec4::new(0.0, 2.0 / size.y as f32, 0.0, 0.0),
+            Vec4::new(2.0 * scale_factor / size.x as f32, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 * scale_factor / size.y as f32, 0.0, 0.0),
             Vec4::new(0.0, 0.0, 1.0, 0.0),
             Vec4::new(0.0, 0.0, 0.0, 1.0),
         );
@@ -50,13 +66,13 @@ impl UiCamera2D {
 mod test {
     use crate::assert_f32_eq;
     use crate::ui_camera::UiCamera2D;
-    use glam::{vec4, UVec2, Vec4};
+    use glam::{vec2, vec4, Affine2, Mat4, UVec2, Vec4};
 
     #[test]
     fn test_camera_transform() {
         let mut cam = UiCamera2D::default();
 
-        cam.resize_viewport(UVec2::new(800, 600));
+        cam.resize_viewport(UVec2::new(800, 600), 1.0);
 
         let points_to_test = vec![
             (vec4(0.0, 0.0, 0.0, 1.0), vec4(-1.0, 1.0, 0.0, 0.0)),
@@ -70,6 +86,44 @@ mod test {
         });
     }
 
+    #[test]
+    fn test_scaled_camera_transform() {
+        let mut cam = UiCamera2D::default();
+
+        // A 1600x1200 pixel target at a scale factor of 2 spans 800x600 logical units
+        cam.resize_viewport(UVec2::new(1600, 1200), 2.0);
+
+        check_transform(vec4(0.0, 0.0, 0.0, 1.0), vec4(-1.0, 1.0, 0.0, 0.0), &cam);
+        check_transform(
+            vec4(800.0, 600.0, 0.0, 1.0),
+            vec4(1.0, -1.0, 0.0, 0.0),
+            &cam,
+        );
+        check_transform(vec4(400.0, 300.0, 0.0, 1.0), vec4(0.0, 0.0, 0.0, 0.0), &cam);
+    }
+
+    #[test]
+    fn test_transformed_camera_uniform() {
+        let mut cam = UiCamera2D::default();
+
+        cam.resize_viewport(UVec2::new(800, 600), 1.0);
+
+        // Moving the origin to the center of the viewport
+        let uniform =
+            cam.create_transformed_camera_uniform(Affine2::from_translation(vec2(400.0, 300.0)));
+        let ndc = Mat4::from_cols_array_2d(&uniform.transform).mul_vec4(vec4(0.0, 0.0, 0.0, 1.0));
+
+        assert_f32_eq!(ndc.x, 0.0, "ndc x");
+        assert_f32_eq!(ndc.y, 0.0, "ndc y");
+
+        let uniform = cam.create_transformed_camera_uniform(Affine2::from_scale(vec2(2.0, 2.0)));
+        let ndc =
+            Mat4::from_cols_array_2d(&uniform.transform).mul_vec4(vec4(400.0, 300.0, 0.0, 1.0));
+
+        assert_f32_eq!(ndc.x, 1.0, "scaled ndc x");
+        assert_f32_eq!(ndc.y, -1.0, "scaled ndc y");
+    }
+
     fn check_transform(pixel_coord: Vec4, ndc_space: Vec4, cam: &UiCamera2D) {
         let pixel_ndc = cam.transform().mul_vec4(pixel_coord);
 
@@ -1,11 +1,13 @@
 use crate::drawable_primitive::DrawablePrimitive;
-use crate::quirky_app_context::QuirkyAppContext;
-use crate::render_contexts::PrepareContext;
+use crate::drawable_tree::Invalidations;
+use crate::opacity_groups::OpacityGroup;
+use crate::quirky_app_context::{QuirkyAppContext, QuirkyResources, SharedResources};
 use crate::widgets::events::WidgetEvent;
 use crate::LayoutBox;
 use futures::{Stream, StreamExt};
 use futures_signals::signal::{always, ReadOnlyMutable, Signal};
-use glam::UVec2;
+use glam::{Affine2, Vec2};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -20,13 +22,26 @@ pub struct WidgetSettings {
 
 pub trait WidgetBase {
     fn id(&self) -> Uuid;
+    /// The name of the widget struct, for debugging and inspection
+    fn type_name(&self) -> &'static str;
     fn bounding_box(&self) -> ReadOnlyMutable<LayoutBox>;
     fn set_bounding_box(&self, new_box: LayoutBox);
+    /// Maps the logical space of this widget and its descendants into the space of its parent,
+    /// applying to both drawing and hit testing
+    fn transform(&self) -> ReadOnlyMutable<Option<Affine2>>;
+    /// Marks the widget dirty when the transform changes; a redraw still needs to be signaled
+    fn set_transform(&self, transform: Option<Affine2>);
     fn dirty(&self) -> ReadOnlyMutable<bool>;
     fn set_dirty(&self);
     fn clear_dirty(&self);
-    fn get_cached_primitives(&self) -> Option<Vec<Box<dyn DrawablePrimitive>>>;
-    fn set_cached_primitives(&self, primitives: Option<Vec<Box<dyn DrawablePrimitive>>>) -> ();
+    /// Number of primitives the widget was last drawn with
+    fn cached_primitive_count(&self) -> usize;
+    fn set_cached_primitive_count(&self, count: usize);
+    /// Where the widget reports that it changed; the box, transform, dirty flag and props
+    /// report it, and so should anything else that changes what the widget draws
+    fn set_invalidations(&self, invalidations: Invalidations);
+    /// Has the next frame read the widget again, see [`Invalidations::invalidate`]
+    fn invalidate(&self);
     fn poll_prop_futures<'a>(
         &'a self,
         ctx: &'a QuirkyAppContext,
@@ -50,10 +65,20 @@ pub trait Widget: WidgetBase + Send + Sync {
         None
     }
 
+    /// Does the expensive cpu work of [`Widget::prepare`] ahead of it, such as shaping text.
+    ///
+    /// The dirty widgets of a frame run this in parallel, and then `prepare` one after the other,
+    /// so only the resources inserted with [`QuirkyResources::insert_shared`] are available here.
+    fn prepare_parallel(&self, _quirky_context: &QuirkyAppContext, _resources: &SharedResources) {}
+
+    /// Creates the primitives drawing this widget.
+    ///
+    /// This runs on the cpu only; primitives create and upload their gpu resources in
+    /// [`DrawablePrimitive::prepare`].
     fn prepare(
         &self,
         _quirky_context: &QuirkyAppContext,
-        _paint_ctx: &mut PrepareContext,
+        _resources: &mut QuirkyResources,
     ) -> Vec<Box<dyn DrawablePrimitive>> {
         vec![]
     }
@@ -62,11 +87,25 @@ pub trait Widget: WidgetBase + Send + Sync {
         Box::new(always(SizeConstraint::Unconstrained))
     }
 
-    fn get_widget_at(&self, _pos: UVec2, _path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    /// Restricts drawing of this widget and its descendants to a rect, within any enclosing clip
+    fn clip_rect(&self) -> Option<LayoutBox> {
+        None
+    }
+
+    /// Draws this widget and its descendants into a texture of their own, composited with the
+    /// group's opacity and blend mode. The group is clipped to the widget's bounding box.
+    fn opacity_group(&self) -> Option<OpacityGroup> {
+        None
+    }
+
+    /// Finds the widget at `pos`, given in the space of this widget.
+    ///
+    /// Children should be visited through [`hit_test`], which applies their transforms.
+    fn get_widget_at(&self, _pos: Vec2, _path: Vec<Uuid>) -> Option<Vec<Uuid>> {
         self.children()
             .map(|children| {
                 for child in children.iter().rev() {
-                    if let Some(hit) = child.get_widget_at(_pos, _path.clone()) {
+                    if let Some(hit) = hit_test(child, _pos, _path.clone()) {
                         return Some(hit);
                     }
                 }
@@ -79,6 +118,16 @@ pub trait Widget: WidgetBase + Send + Sync {
     async fn run(self: Arc<Self>, ctx: &QuirkyAppContext);
 }
 
+/// Hit tests `widget` at `pos`, given in the space of its parent
+pub fn hit_test(widget: &Arc<dyn Widget>, pos: Vec2, path: Vec<Uuid>) -> Option<Vec<Uuid>> {
+    let pos = match widget.transform().get() {
+        Some(transform) => transform.inverse().transform_point2(pos),
+        None => pos,
+    };
+
+    widget.get_widget_at(pos, path)
+}
+
 pub async fn default_run(widget: Arc<dyn Widget>, ctx: &QuirkyAppContext) {
     let mut futs = widget.poll_prop_futures(ctx);
 
@@ -87,12 +136,46 @@ pub async fn default_run(widget: Arc<dyn Widget>, ctx: &QuirkyAppContext) {
     }
 }
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+/// The size a widget asks its layout for, in logical units
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SizeConstraint {
-    MinSize(UVec2),
-    MaxSize(UVec2),
+    MinSize(Vec2),
+    MaxSize(Vec2),
     #[default]
     Unconstrained,
-    MaxHeight(u32),
-    MaxWidth(u32),
+    MaxHeight(f32),
+    MaxWidth(f32),
+}
+
+#[cfg(test)]
+mod test {
+    use crate::drawable_tree::test::TestWidget;
+    use crate::widget::{hit_test, Widget, WidgetBase};
+    use crate::LayoutBox;
+    use glam::{vec2, Affine2};
+    use std::sync::Arc;
+
+    #[test]
+    fn transformed_widgets_are_hit_in_their_own_space() {
+        let widget: Arc<dyn Widget> = TestWidget::new(0.0, 0.0, vec![]);
+
+        widget.set_bounding_box(LayoutBox {
+            pos: vec2(0.0, 0.0),
+            size: vec2(10.0, 10.0),
+        });
+        widget.clear_dirty();
+        widget.set_transform(Some(Affine2::from_scale_angle_translation(
+            vec2(2.0, 2.0),
+            0.0,
+            vec2(100.0, 0.0),
+        )));
+
+        assert!(widget.dirty().get());
+        assert_eq!(
+            hit_test(&widget, vec2(115.0, 15.0), vec![]),
+            Some(vec![widget.id()])
+        );
+        assert_eq!(hit_test(&widget, vec2(5.0, 5.0), vec![]), None);
+        assert_eq!(hit_test(&widget, vec2(125.0, 5.0), vec![]), None);
+    }
 }
@@ -1,7 +1,8 @@
 use glam::UVec2;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum MouseButton {
     Left,
     Right,
@@ -9,7 +10,7 @@ pub enum MouseButton {
     Num(usize),
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 #[repr(u32)]
 pub enum KeyCode {
     Key1,
@@ -128,14 +129,14 @@ pub enum KeyCode {
     Unknown,
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct KeyboardModifier {
     pub alt: bool,
     pub shift: bool,
     pub ctrl: bool,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum KeyboardEvent {
     KeyPressed {
         key_code: KeyCode,
@@ -143,7 +144,7 @@ pub enum KeyboardEvent {
     },
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum MouseEvent {
     Enter {
         pos: UVec2,
@@ -165,20 +166,20 @@ pub enum MouseEvent {
     },
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum FocusState {
     Focused,
     Unfocused,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum WidgetEvent {
     KeyboardEvent { event: KeyboardEvent },
     MouseEvent { event: MouseEvent },
     FocusChange(FocusState),
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct EventDispatch {
     pub receiver_id: Uuid,
     pub event: WidgetEvent,
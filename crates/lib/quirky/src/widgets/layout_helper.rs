@@ -4,6 +4,9 @@ use futures_signals::map_ref;
 use futures_signals::signal::Signal;
 use futures_signals::signal_vec::{SignalVec, SignalVecExt};
 
+/// Runs `layout_strategy` whenever the container box, the child constraints or the extras change.
+///
+/// Boxes are in fractional logical units, strategies should divide space without rounding.
 pub fn layout<TExtras: Send>(
     container_box: impl Signal<Item = LayoutBox> + Send,
     constraints: impl SignalVec<Item = Box<dyn Signal<Item = SizeConstraint> + Unpin + Send>> + Send,
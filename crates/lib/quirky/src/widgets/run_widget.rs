@@ -10,6 +10,7 @@ use quirky_utils::futures_map_poll::FuturesMapPoll;
 use std::collections::HashSet;
 use std::future::Future;
 use std::sync::Arc;
+use tracing::Instrument;
 use uuid::Uuid;
 
 pub fn run_widgets<'a>(
@@ -32,7 +33,14 @@ pub fn run_widgets<'a>(
                 let id = widget.id();
 
                 if !widgets_futures_lock.contains_key(&id) {
-                    widgets_futures_lock.insert(id, widget.clone().run(ctx).boxed().into());
+                    let span = tracing::trace_span!(
+                        "widget_run",
+                        widget = widget.type_name(),
+                        %id
+                    );
+
+                    widgets_futures_lock
+                        .insert(id, widget.clone().run(ctx).instrument(span).boxed().into());
                 }
             }
 